@@ -0,0 +1,1097 @@
+use crate::api;
+use crate::app::{self, App, Focusable, FolderPickerAction, InputMode, ResultsSource};
+use crate::command::{self, Command};
+use crate::task::AppEvent;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::widgets::ListState;
+use tui_input::{Input, backend::crossterm::EventHandler};
+
+/// Everything that changes the app: what a key asks for, or a background task's outcome.
+/// [`map_key`] turns keys into actions and [`App::update`] carries them out.
+pub enum Action {
+    Quit,
+    /// Moves the highlight in the list the current view or popup shows.
+    SelectNext,
+    SelectPrev,
+    FocusNext,
+    FocusPrev,
+    /// Opens, plays or submits whatever is highlighted or typed.
+    Confirm,
+    /// Closes the current popup or view.
+    Back,
+    /// Starts typing into the search bar, emptying it first with `clear`.
+    StartSearch {
+        clear: bool,
+    },
+    /// Starts typing into the command line, pre-filled with the given text.
+    StartCommand(String),
+    /// A key typed into the search bar or command line.
+    Type(KeyEvent),
+    Play,
+    PlayAll,
+    Like,
+    GiveCoins(u8),
+    Triple,
+    SelectTag {
+        forward: bool,
+    },
+    ShowTag,
+    ShowUploader,
+    Favorite,
+    WatchLater,
+    MoveToFolder,
+    CopyToFolder,
+    ToggleFolder,
+    /// Turns the page, issue, zone or live tab.
+    ChangePage {
+        forward: bool,
+    },
+    SortUploads,
+    RefreshTab,
+    LoadMore,
+    /// Removes the highlighted entry from Watch Later, the history or the recordings.
+    Delete,
+    ClearWatched,
+    Dislike(api::DislikeReason),
+    Record,
+    StopRecording,
+    LeaveChat,
+    TaskFinished(Result<AppEvent, String>),
+}
+
+/// What `key` means in the app's current mode, if anything.
+pub fn map_key(app: &App, key: KeyEvent) -> Option<Action> {
+    let code = key.code;
+    if app.folder_picker.is_some() {
+        return match code {
+            KeyCode::Char('j') => Some(Action::SelectNext),
+            KeyCode::Char('k') => Some(Action::SelectPrev),
+            KeyCode::Char(' ') => Some(Action::ToggleFolder),
+            KeyCode::Enter => Some(Action::Confirm),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Back),
+            _ => None,
+        };
+    }
+    if app.quality_picker.is_some() {
+        return match code {
+            KeyCode::Char('j') => Some(Action::SelectNext),
+            KeyCode::Char('k') => Some(Action::SelectPrev),
+            KeyCode::Enter => Some(Action::Confirm),
+            KeyCode::Char('q') | KeyCode::Esc => Some(Action::Back),
+            _ => None,
+        };
+    }
+
+    let showing_videos = matches!(
+        app.mode,
+        InputMode::ListNav
+            | InputMode::Detail
+            | InputMode::Uploader
+            | InputMode::History
+            | InputMode::Feed
+    );
+    let action = match (app.mode, code) {
+        (_, KeyCode::Char('f')) if showing_videos => Action::Favorite,
+        (_, KeyCode::Char('w')) if showing_videos => Action::WatchLater,
+        (InputMode::Help, _) => Action::Back,
+
+        (InputMode::Editing | InputMode::Command, KeyCode::Enter) => Action::Confirm,
+        (InputMode::Editing | InputMode::Command, KeyCode::Char('q') | KeyCode::Esc) => {
+            Action::Back
+        }
+        (InputMode::Editing | InputMode::Command, _) => Action::Type(key),
+
+        (InputMode::Start | InputMode::Normal, KeyCode::Char('q')) => Action::Quit,
+        (InputMode::Normal, KeyCode::Char('j')) => Action::FocusNext,
+        (InputMode::Normal, KeyCode::Char('k')) => Action::FocusPrev,
+        (InputMode::Start, KeyCode::Char(':')) => Action::StartCommand(":".to_string()),
+        (InputMode::Normal, KeyCode::Char(':')) if app.focused_panel == Focusable::Command => {
+            Action::StartCommand(":".to_string())
+        }
+        (InputMode::Start | InputMode::Normal, KeyCode::Char('/')) => {
+            Action::StartSearch { clear: false }
+        }
+        (InputMode::Start, KeyCode::Esc) => Action::Back,
+
+        (InputMode::Detail, KeyCode::Char('j')) if app.focused_panel != Focusable::Results => {
+            Action::FocusNext
+        }
+        (InputMode::Detail, KeyCode::Char('k')) if app.focused_panel != Focusable::Results => {
+            Action::FocusPrev
+        }
+        (InputMode::Detail, KeyCode::Tab) => Action::FocusNext,
+        (InputMode::Detail, KeyCode::BackTab) => Action::FocusPrev,
+        (InputMode::Detail, KeyCode::Char('l')) => Action::Like,
+        (InputMode::Detail, KeyCode::Char('c')) => Action::GiveCoins(1),
+        (InputMode::Detail, KeyCode::Char('C')) => Action::GiveCoins(2),
+        (InputMode::Detail, KeyCode::Char('t')) => Action::Triple,
+        (InputMode::Detail, KeyCode::Char('[')) => Action::SelectTag { forward: false },
+        (InputMode::Detail, KeyCode::Char(']')) => Action::SelectTag { forward: true },
+        (InputMode::Detail, KeyCode::Char('#')) => Action::ShowTag,
+        (InputMode::Detail, KeyCode::Char('u')) => Action::ShowUploader,
+
+        (InputMode::ListNav | InputMode::Uploader | InputMode::Live, KeyCode::Char('h')) => {
+            Action::ChangePage { forward: false }
+        }
+        (InputMode::ListNav | InputMode::Uploader | InputMode::Live, KeyCode::Char('l')) => {
+            Action::ChangePage { forward: true }
+        }
+        (InputMode::ListNav, KeyCode::Char('m')) if is_favorites(app) => Action::MoveToFolder,
+        (InputMode::ListNav, KeyCode::Char('c')) if is_favorites(app) => Action::CopyToFolder,
+        (InputMode::ListNav, KeyCode::Char('d')) if is_watch_later(app) => Action::Delete,
+        (InputMode::ListNav, KeyCode::Char('C')) if is_watch_later(app) => Action::ClearWatched,
+        (InputMode::ListNav, KeyCode::Char('x')) if is_recommended(app) => {
+            Action::Dislike(api::DislikeReason::Video)
+        }
+        (InputMode::ListNav, KeyCode::Char('X')) if is_recommended(app) => {
+            Action::Dislike(api::DislikeReason::Uploader)
+        }
+        (InputMode::ListNav, KeyCode::Char('P')) => Action::PlayAll,
+        (InputMode::ListNav, KeyCode::Char('/')) => Action::StartSearch { clear: true },
+
+        (InputMode::Favorites, KeyCode::Char('n')) => Action::StartCommand(":fav-new ".to_string()),
+        (InputMode::Favorites, KeyCode::Char('r')) => {
+            let folder = app.favorites.as_ref()?.selected_folder()?;
+            Action::StartCommand(format!(":fav-rename {}", folder.title))
+        }
+
+        (InputMode::Uploader, KeyCode::Char('s')) => Action::SortUploads,
+        (InputMode::History | InputMode::Feed, KeyCode::Char('l')) => Action::LoadMore,
+        (InputMode::History, KeyCode::Char('d')) => Action::Delete,
+
+        (InputMode::Live, KeyCode::Char('r')) => Action::RefreshTab,
+        (InputMode::Live | InputMode::Season, KeyCode::Char('p')) => Action::Confirm,
+        (InputMode::Live, KeyCode::Char('R')) => Action::Record,
+        (InputMode::Live, KeyCode::Char('c')) => Action::LeaveChat,
+
+        (InputMode::Recordings, KeyCode::Char('s')) => Action::StopRecording,
+        (InputMode::Recordings, KeyCode::Char('d')) => Action::Delete,
+
+        (
+            InputMode::Detail | InputMode::Uploader | InputMode::History | InputMode::Feed,
+            KeyCode::Char('p'),
+        ) => Action::Play,
+        (_, KeyCode::Enter) => Action::Confirm,
+        (InputMode::Normal, _) => return None,
+        (_, KeyCode::Char('j')) => Action::SelectNext,
+        (_, KeyCode::Char('k')) => Action::SelectPrev,
+        (InputMode::Start, _) => return None,
+        (_, KeyCode::Char('q') | KeyCode::Esc) => Action::Back,
+        _ => return None,
+    };
+    Some(action)
+}
+
+fn is_favorites(app: &App) -> bool {
+    matches!(app.results_source, ResultsSource::Favorites(_))
+}
+
+fn is_watch_later(app: &App) -> bool {
+    matches!(app.results_source, ResultsSource::WatchLater)
+}
+
+fn is_recommended(app: &App) -> bool {
+    matches!(app.results_source, ResultsSource::Recommended(_))
+}
+
+impl App {
+    /// Carries out `action`. Network work it needs is queued to run in the background, and comes
+    /// back as an [`Action::TaskFinished`]; anything else outside the app, such as starting mpv,
+    /// is queued in [`App::effects`]. The main loop starts both once the update is done.
+    pub fn update(&mut self, action: Action) {
+        match action {
+            Action::Quit => self.should_quit = true,
+            Action::SelectNext => self.move_selection(true),
+            Action::SelectPrev => self.move_selection(false),
+            Action::FocusNext => self.focused_panel = self.focused_panel.next(),
+            Action::FocusPrev => self.focused_panel = self.focused_panel.prev(),
+            Action::Confirm => self.confirm(),
+            Action::Back => self.back(),
+            Action::StartSearch { clear } => {
+                if clear {
+                    self.search_input.reset();
+                }
+                self.focused_panel = Focusable::Search;
+                self.mode = InputMode::Editing;
+            }
+            Action::StartCommand(prefill) => {
+                self.command_input = Input::new(prefill);
+                self.focused_panel = Focusable::Command;
+                self.mode = InputMode::Command;
+            }
+            Action::Type(key) => {
+                let input = match self.mode {
+                    InputMode::Editing => &mut self.search_input,
+                    InputMode::Command => &mut self.command_input,
+                    _ => return,
+                };
+                input.handle_event(&Event::Key(key));
+            }
+            Action::Play => self.play_video(),
+            Action::PlayAll => self.play_all(),
+            Action::Like => {
+                let result = command::toggle_like(self);
+                self.report(result);
+            }
+            Action::GiveCoins(coins) => {
+                let result = command::give_coins(coins, self);
+                self.report(result);
+            }
+            Action::Triple => {
+                let result = command::triple(self);
+                self.report(result);
+            }
+            Action::SelectTag { forward } => self.select_tag(forward),
+            Action::ShowTag => {
+                if let Some(name) = self.selected_tag_name() {
+                    self.run(Command::ShowTag(name));
+                }
+            }
+            Action::ShowUploader => {
+                let uploader = match &self.video_info {
+                    Some(info) => Some(info.owner.mid.to_string()),
+                    // Search results carry the uploader's id; fall back to looking the name up.
+                    None => self.selected_result().map(|v| {
+                        if v.mid != 0 {
+                            v.mid.to_string()
+                        } else {
+                            v.author.clone()
+                        }
+                    }),
+                };
+                if let Some(uploader) = uploader {
+                    self.run(Command::ShowUploader(uploader));
+                }
+            }
+            Action::Favorite => {
+                if let Some(bvid) = self.current_bvid() {
+                    command::open_folder_picker(&bvid, FolderPickerAction::Favorite, self);
+                }
+            }
+            Action::WatchLater => {
+                if let Some(bvid) = self.current_bvid() {
+                    command::add_to_watch_later(&bvid, self);
+                }
+            }
+            Action::MoveToFolder | Action::CopyToFolder => {
+                if let ResultsSource::Favorites(fav) = &self.results_source
+                    && let Some(bvid) = self.current_bvid()
+                {
+                    let from = fav.folder.id;
+                    let action = if matches!(action, Action::MoveToFolder) {
+                        FolderPickerAction::Move { from }
+                    } else {
+                        FolderPickerAction::Copy { from }
+                    };
+                    command::open_folder_picker(&bvid, action, self);
+                }
+            }
+            Action::ToggleFolder => {
+                if let Some(picker) = self.folder_picker.as_mut() {
+                    picker.toggle();
+                }
+            }
+            Action::ChangePage { forward } => self.change_page(forward),
+            Action::SortUploads => {
+                if let Some(view) = &self.uploader {
+                    command::load_uploader_page(1, view.order.next(), self);
+                }
+            }
+            Action::RefreshTab => {
+                if let Some(view) = &self.live {
+                    command::load_live_tab(view.tab, self);
+                }
+            }
+            Action::LoadMore => match self.mode {
+                InputMode::History
+                    if self
+                        .history
+                        .as_ref()
+                        .is_some_and(|view| view.next.is_some()) =>
+                {
+                    command::load_history_page(self)
+                }
+                InputMode::Feed if self.feed.as_ref().is_some_and(|view| view.has_more) => {
+                    command::load_feed_page(self)
+                }
+                _ => {}
+            },
+            Action::Delete => match self.mode {
+                InputMode::ListNav if matches!(self.results_source, ResultsSource::WatchLater) => {
+                    command::remove_from_watch_later(self)
+                }
+                InputMode::History => command::delete_history_entry(self),
+                InputMode::Recordings => {
+                    // Only stopped recordings can be dismissed.
+                    let selected = self.recordings_list_state.selected();
+                    if let Some(i) = selected
+                        .filter(|&i| self.recordings.get(i).is_some_and(|r| r.stopped.is_some()))
+                    {
+                        self.recordings.remove(i);
+                        let len = self.recordings.len();
+                        self.recordings_list_state
+                            .select(len.checked_sub(1).map(|last| i.min(last)));
+                    }
+                }
+                _ => {}
+            },
+            Action::ClearWatched => command::clear_watched_watch_later(self),
+            Action::Dislike(reason) => command::dislike_recommendation(reason, self),
+            Action::Record => {
+                if let Some(room) = self.live.as_ref().and_then(|view| view.selected()) {
+                    command::start_recording(room.room_id, self);
+                }
+            }
+            Action::StopRecording => {
+                let selected = self.recordings_list_state.selected();
+                if let Some(recording) = selected.and_then(|i| self.recordings.get_mut(i)) {
+                    recording.stop();
+                    self.last_error = Some(format!("Stopped recording room {}", recording.room_id));
+                }
+            }
+            Action::LeaveChat => self.live_chat = None,
+            Action::TaskFinished(result) => {
+                if let Err(e) = result.and_then(|event| command::apply(event, self)) {
+                    self.last_error = Some(e);
+                }
+            }
+        }
+    }
+
+    /// Runs `command`, replacing any earlier message with its outcome.
+    fn run(&mut self, command: Command) {
+        self.last_error = None;
+        if let Err(e) = command::execute(command, self) {
+            self.last_error = Some(e);
+        }
+    }
+
+    /// Opens `bvid` in the detail view, returning to `parent` when it is closed.
+    fn open_video(&mut self, bvid: String, parent: InputMode) {
+        self.run(Command::ShowVideoInfo(bvid));
+        if self.mode == InputMode::Detail {
+            self.detail_parent = Some(parent);
+        }
+    }
+
+    fn move_selection(&mut self, forward: bool) {
+        let (state, len): (&mut ListState, usize) = if let Some(picker) =
+            self.folder_picker.as_mut()
+        {
+            (&mut picker.list_state, picker.folders.len())
+        } else if let Some(picker) = self.quality_picker.as_mut() {
+            (&mut picker.list_state, picker.qualities.len())
+        } else {
+            match self.mode {
+                InputMode::Start => (&mut self.start_list_state, app::START_MENU.len()),
+                InputMode::Detail => (&mut self.related_list_state, self.related.len()),
+                InputMode::ListNav => (&mut self.results_list_state, self.search_results.len()),
+                InputMode::Favorites => match self.favorites.as_mut() {
+                    Some(view) => (&mut view.list_state, view.folders.len()),
+                    None => return,
+                },
+                InputMode::Uploader => match self.uploader.as_mut() {
+                    Some(view) => (&mut view.list_state, view.videos.len()),
+                    None => return,
+                },
+                InputMode::History => match self.history.as_mut() {
+                    Some(view) => (&mut view.list_state, view.entries.len()),
+                    None => return,
+                },
+                InputMode::Feed => match self.feed.as_mut() {
+                    Some(view) => (&mut view.list_state, view.items.len()),
+                    None => return,
+                },
+                InputMode::Live => match self.live.as_mut() {
+                    Some(view) => (&mut view.list_state, view.rooms.len()),
+                    None => return,
+                },
+                InputMode::Season => match self.season.as_mut() {
+                    Some(view) => (&mut view.list_state, view.season.episodes.len()),
+                    None => return,
+                },
+                InputMode::Recordings => (&mut self.recordings_list_state, self.recordings.len()),
+                InputMode::Normal | InputMode::Editing | InputMode::Command | InputMode::Help => {
+                    return;
+                }
+            }
+        };
+        if forward {
+            app::select_next(state, len);
+        } else {
+            app::select_prev(state, len);
+        }
+    }
+
+    fn confirm(&mut self) {
+        if self.folder_picker.is_some() {
+            command::apply_folder_picker(self);
+            return;
+        }
+        if self.quality_picker.is_some() {
+            command::play_picked_quality(self);
+            return;
+        }
+        match self.mode {
+            InputMode::Start => {
+                if let Some(i) = self.start_list_state.selected() {
+                    let (_, command_str) = app::START_MENU[i];
+                    match command::parse(command_str) {
+                        Ok(command) => self.run(command),
+                        Err(e) => self.last_error = Some(e),
+                    }
+                }
+            }
+            InputMode::Normal | InputMode::Detail => match self.focused_panel {
+                Focusable::Search => self.mode = InputMode::Editing,
+                Focusable::Command => self.mode = InputMode::Command,
+                Focusable::Results if self.mode == InputMode::Detail => {
+                    if let Some(bvid) = self.selected_related_bvid() {
+                        self.run(Command::ShowVideoInfo(bvid));
+                    }
+                }
+                Focusable::Results if self.search_results.is_empty() => {
+                    self.mode = InputMode::Start
+                }
+                Focusable::Results => self.mode = InputMode::ListNav,
+                Focusable::None => {}
+            },
+            InputMode::Editing => {
+                let query = self.search_input.value().to_string();
+                if let ResultsSource::Favorites(fav) = &self.results_source {
+                    let folder = fav.folder.clone();
+                    command::open_fav_folder(folder, 1, query, self);
+                    return;
+                }
+                command::search(query, self);
+                self.last_error = None;
+                self.mode = InputMode::Normal;
+            }
+            InputMode::Command => {
+                let command_str = self.command_input.value().to_string();
+                self.command_input.reset();
+                self.mode = InputMode::Normal;
+                match command::parse(&command_str) {
+                    Ok(Command::Quit) => self.should_quit = true,
+                    Ok(command) => self.run(command),
+                    Err(e) => self.last_error = Some(e),
+                }
+            }
+            InputMode::ListNav => {
+                if let Some(bvid) = self.current_bvid() {
+                    command::open_details(&bvid, self);
+                    command::prefetch_neighbors(self);
+                    if !matches!(self.results_source, ResultsSource::Search) {
+                        self.detail_parent = Some(InputMode::ListNav);
+                    }
+                }
+            }
+            InputMode::Favorites => {
+                let folder = self
+                    .favorites
+                    .as_ref()
+                    .and_then(|view| view.selected_folder())
+                    .cloned();
+                if let Some(folder) = folder {
+                    self.search_input.reset();
+                    command::open_fav_folder(folder, 1, String::new(), self);
+                }
+            }
+            InputMode::Uploader | InputMode::History | InputMode::Feed => {
+                if let Some(bvid) = self.current_bvid() {
+                    self.open_video(bvid, self.mode);
+                }
+            }
+            InputMode::Live => {
+                if let Some(room) = self.live.as_ref().and_then(|view| view.selected()) {
+                    command::open_quality_picker(room.room_id, self);
+                }
+            }
+            InputMode::Season => command::play_episode(self),
+            InputMode::Recordings | InputMode::Help => {}
+        }
+    }
+
+    fn back(&mut self) {
+        if self.folder_picker.take().is_some() || self.quality_picker.take().is_some() {
+            return;
+        }
+        match self.mode {
+            InputMode::Detail => {
+                self.close_detail();
+                return;
+            }
+            InputMode::ListNav => {
+                if let ResultsSource::Favorites(_) = self.results_source {
+                    self.set_results(Vec::new(), ResultsSource::Search);
+                    self.mode = InputMode::Favorites;
+                    return;
+                }
+                self.results_list_state.select(None);
+            }
+            InputMode::Favorites => self.favorites = None,
+            InputMode::Uploader => self.uploader = None,
+            InputMode::History => self.history = None,
+            InputMode::Feed => self.feed = None,
+            InputMode::Live => {
+                self.live = None;
+                self.live_chat = None;
+            }
+            InputMode::Season => self.season = None,
+            // Help only returns to the normal mode, keeping the focus where it was.
+            InputMode::Help => {
+                self.mode = InputMode::Normal;
+                return;
+            }
+            InputMode::Start
+            | InputMode::Normal
+            | InputMode::Editing
+            | InputMode::Command
+            | InputMode::Recordings => {}
+        }
+        self.mode = InputMode::Normal;
+        self.focused_panel = Focusable::None;
+    }
+
+    fn change_page(&mut self, forward: bool) {
+        match self.mode {
+            InputMode::ListNav => command::change_page(forward, self),
+            InputMode::Uploader => {
+                let Some(view) = &self.uploader else {
+                    return;
+                };
+                let page = match forward {
+                    true if view.page < view.page_count() => view.page + 1,
+                    false if view.page > 1 => view.page - 1,
+                    _ => return,
+                };
+                command::load_uploader_page(page, view.order, self);
+            }
+            InputMode::Live => {
+                let Some(view) = &self.live else {
+                    return;
+                };
+                let count = view.tabs.len();
+                let tab = if forward {
+                    (view.tab + 1) % count
+                } else {
+                    (view.tab + count - 1) % count
+                };
+                command::load_live_tab(tab, self);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{FavoritesPage, FavoritesView, QualityPicker, RecommendedFeed};
+    use crate::effect::Effect;
+    use crate::fixtures::{folder, picker, video};
+    use crossterm::event::KeyCode::{BackTab, Char, Enter, Esc, Tab};
+    use crossterm::event::KeyModifiers;
+
+    /// Asserts what each key means in the app's current state.
+    macro_rules! assert_keys {
+        ($app:expr, $($code:expr => $action:pat $(if $guard:expr)?),+ $(,)?) => {
+            $(
+                // `None` is one of the patterns, which clippy would rather see as `is_none()`.
+                #[allow(clippy::redundant_pattern_matching)]
+                let matched = matches!(key(&$app, $code), $action $(if $guard)?);
+                assert!(
+                    matched,
+                    "{:?} in {:?}",
+                    $code,
+                    $app.mode,
+                );
+            )+
+        };
+    }
+
+    fn key(app: &App, code: KeyCode) -> Option<Action> {
+        map_key(app, KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn app_in(mode: InputMode) -> App {
+        let mut app = App::new();
+        app.mode = mode;
+        app
+    }
+
+    /// Handles `code` the way the main loop does.
+    fn press(app: &mut App, code: KeyCode) {
+        if let Some(action) = key(app, code) {
+            app.update(action);
+        }
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, Char(c));
+        }
+    }
+
+    fn show(app: &mut App, count: usize, source: ResultsSource) {
+        let videos = (1..=count).map(|i| video(&format!("BV{}", i))).collect();
+        app.update(Action::TaskFinished(Ok(AppEvent::Results {
+            videos,
+            source,
+            focus: true,
+        })));
+    }
+
+    #[test]
+    fn maps_keys_on_the_start_screen() {
+        let app = app_in(InputMode::Start);
+        assert_keys!(app,
+            Char('q') => Some(Action::Quit),
+            Char('/') => Some(Action::StartSearch { clear: false }),
+            Char(':') => Some(Action::StartCommand(ref text)) if text == ":",
+            Char('j') => Some(Action::SelectNext),
+            Char('k') => Some(Action::SelectPrev),
+            Enter => Some(Action::Confirm),
+            Esc => Some(Action::Back),
+            Char('p') => None,
+            Char('f') => None,
+            Char('h') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_with_nothing_open() {
+        let mut app = app_in(InputMode::Normal);
+        assert_keys!(app,
+            Char('q') => Some(Action::Quit),
+            Char('j') => Some(Action::FocusNext),
+            Char('k') => Some(Action::FocusPrev),
+            Char('/') => Some(Action::StartSearch { clear: false }),
+            Char(':') => None,
+            Enter => Some(Action::Confirm),
+            Esc => None,
+            Char('f') => None,
+        );
+        app.focused_panel = Focusable::Command;
+        assert_keys!(app, Char(':') => Some(Action::StartCommand(ref text)) if text == ":");
+    }
+
+    #[test]
+    fn maps_keys_in_the_search_bar_and_command_line() {
+        for mode in [InputMode::Editing, InputMode::Command] {
+            let app = app_in(mode);
+            assert_keys!(app,
+                Char('q') => Some(Action::Back),
+                Char('j') => Some(Action::Type(_)),
+                Char('k') => Some(Action::Type(_)),
+                Char('f') => Some(Action::Type(_)),
+                Char('w') => Some(Action::Type(_)),
+                Char('p') => Some(Action::Type(_)),
+                Char(':') => Some(Action::Type(_)),
+                Char('/') => Some(Action::Type(_)),
+                Enter => Some(Action::Confirm),
+                Esc => Some(Action::Back),
+            );
+        }
+    }
+
+    #[test]
+    fn types_j_and_k_but_leaves_the_input_on_q() {
+        let mut app = app_in(InputMode::Start);
+        press(&mut app, Char('/'));
+        type_text(&mut app, "jk");
+        assert_eq!(app.search_input.value(), "jk");
+        assert_eq!(app.mode, InputMode::Editing);
+
+        press(&mut app, Char('q'));
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(app.search_input.value(), "jk");
+        assert!(!app.should_quit);
+
+        app.focused_panel = Focusable::Command;
+        press(&mut app, Char(':'));
+        type_text(&mut app, "j");
+        assert_eq!(app.command_input.value(), ":j");
+        press(&mut app, Char('q'));
+        assert_eq!(app.mode, InputMode::Normal);
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn maps_keys_in_the_results_list() {
+        let app = app_in(InputMode::ListNav);
+        assert_keys!(app,
+            Char('h') => Some(Action::ChangePage { forward: false }),
+            Char('l') => Some(Action::ChangePage { forward: true }),
+            Char('j') => Some(Action::SelectNext),
+            Char('k') => Some(Action::SelectPrev),
+            Enter => Some(Action::Confirm),
+            Char('P') => Some(Action::PlayAll),
+            Char('/') => Some(Action::StartSearch { clear: true }),
+            Char('f') => Some(Action::Favorite),
+            Char('w') => Some(Action::WatchLater),
+            Char('q') => Some(Action::Back),
+            Esc => Some(Action::Back),
+            Char('p') => None,
+            // These depend on what the list shows.
+            Char('m') => None,
+            Char('c') => None,
+            Char('d') => None,
+            Char('C') => None,
+            Char('x') => None,
+            Char('X') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_for_the_list_being_shown() {
+        let mut app = app_in(InputMode::ListNav);
+        app.results_source = ResultsSource::Favorites(FavoritesPage {
+            folder: folder(1, 0),
+            page: 1,
+            has_more: false,
+            keyword: String::new(),
+        });
+        assert_keys!(app,
+            Char('m') => Some(Action::MoveToFolder),
+            Char('c') => Some(Action::CopyToFolder),
+            Char('d') => None,
+            Char('x') => None,
+        );
+
+        app.results_source = ResultsSource::WatchLater;
+        assert_keys!(app,
+            Char('d') => Some(Action::Delete),
+            Char('C') => Some(Action::ClearWatched),
+            Char('m') => None,
+            Char('x') => None,
+        );
+
+        app.results_source = ResultsSource::Recommended(RecommendedFeed {
+            fresh_idx: 1,
+            targets: Default::default(),
+        });
+        assert_keys!(app,
+            Char('x') => Some(Action::Dislike(api::DislikeReason::Video)),
+            Char('X') => Some(Action::Dislike(api::DislikeReason::Uploader)),
+            Char('d') => None,
+            Char('c') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_detail_view() {
+        let app = app_in(InputMode::Detail);
+        assert_keys!(app,
+            Char('p') => Some(Action::Play),
+            Char('l') => Some(Action::Like),
+            Char('c') => Some(Action::GiveCoins(1)),
+            Char('C') => Some(Action::GiveCoins(2)),
+            Char('t') => Some(Action::Triple),
+            Char('[') => Some(Action::SelectTag { forward: false }),
+            Char(']') => Some(Action::SelectTag { forward: true }),
+            Char('#') => Some(Action::ShowTag),
+            Char('u') => Some(Action::ShowUploader),
+            Char('f') => Some(Action::Favorite),
+            Char('w') => Some(Action::WatchLater),
+            Tab => Some(Action::FocusNext),
+            BackTab => Some(Action::FocusPrev),
+            Enter => Some(Action::Confirm),
+            Char('q') => Some(Action::Back),
+            Char('h') => None,
+            Char('P') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_favorites_view() {
+        let mut app = app_in(InputMode::Favorites);
+        assert_keys!(app,
+            Char('n') => Some(Action::StartCommand(ref text)) if text == ":fav-new ",
+            // Nothing to rename without a folder.
+            Char('r') => None,
+            Char('j') => Some(Action::SelectNext),
+            Enter => Some(Action::Confirm),
+            Char('q') => Some(Action::Back),
+            Char('f') => None,
+            Char('p') => None,
+        );
+
+        app.favorites = Some(FavoritesView {
+            folders: vec![folder(1, 0)],
+            list_state: ListState::default().with_selected(Some(0)),
+        });
+        assert_keys!(app,
+            Char('r') => Some(Action::StartCommand(ref text)) if text == ":fav-rename folder 1",
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_an_uploaders_videos() {
+        let app = app_in(InputMode::Uploader);
+        assert_keys!(app,
+            Char('s') => Some(Action::SortUploads),
+            Char('h') => Some(Action::ChangePage { forward: false }),
+            Char('l') => Some(Action::ChangePage { forward: true }),
+            Char('p') => Some(Action::Play),
+            Char('f') => Some(Action::Favorite),
+            Char('w') => Some(Action::WatchLater),
+            Enter => Some(Action::Confirm),
+            Char('q') => Some(Action::Back),
+            Char('d') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_history_and_the_feed() {
+        let app = app_in(InputMode::History);
+        assert_keys!(app,
+            Char('l') => Some(Action::LoadMore),
+            Char('d') => Some(Action::Delete),
+            Char('p') => Some(Action::Play),
+            Char('f') => Some(Action::Favorite),
+            Char('h') => None,
+            Esc => Some(Action::Back),
+        );
+
+        let app = app_in(InputMode::Feed);
+        assert_keys!(app,
+            Char('l') => Some(Action::LoadMore),
+            Char('p') => Some(Action::Play),
+            Char('w') => Some(Action::WatchLater),
+            Char('d') => None,
+            Char('h') => None,
+            Esc => Some(Action::Back),
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_live_rooms_and_seasons() {
+        let app = app_in(InputMode::Live);
+        assert_keys!(app,
+            Char('r') => Some(Action::RefreshTab),
+            Char('p') => Some(Action::Confirm),
+            Char('R') => Some(Action::Record),
+            Char('c') => Some(Action::LeaveChat),
+            Char('h') => Some(Action::ChangePage { forward: false }),
+            Char('l') => Some(Action::ChangePage { forward: true }),
+            Char('q') => Some(Action::Back),
+            Char('f') => None,
+            Char('d') => None,
+        );
+
+        let app = app_in(InputMode::Season);
+        assert_keys!(app,
+            Char('p') => Some(Action::Confirm),
+            Char('j') => Some(Action::SelectNext),
+            Char('q') => Some(Action::Back),
+            Char('l') => None,
+            Char('f') => None,
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_recordings_view() {
+        let app = app_in(InputMode::Recordings);
+        assert_keys!(app,
+            Char('s') => Some(Action::StopRecording),
+            Char('d') => Some(Action::Delete),
+            Char('k') => Some(Action::SelectPrev),
+            Char('q') => Some(Action::Back),
+            Char('p') => None,
+            Char('R') => None,
+        );
+    }
+
+    #[test]
+    fn leaves_the_help_with_any_key() {
+        let app = app_in(InputMode::Help);
+        assert_keys!(app,
+            Char('x') => Some(Action::Back),
+            Char('j') => Some(Action::Back),
+            Char('q') => Some(Action::Back),
+            Enter => Some(Action::Back),
+        );
+    }
+
+    #[test]
+    fn maps_keys_in_the_pickers_over_any_view() {
+        let mut app = app_in(InputMode::ListNav);
+        app.folder_picker = Some(picker(FolderPickerAction::Favorite, vec![folder(1, 0)]));
+        assert_keys!(app,
+            Char(' ') => Some(Action::ToggleFolder),
+            Char('j') => Some(Action::SelectNext),
+            Char('k') => Some(Action::SelectPrev),
+            Enter => Some(Action::Confirm),
+            Char('q') => Some(Action::Back),
+            Esc => Some(Action::Back),
+            Char('l') => None,
+            Char('f') => None,
+        );
+
+        let mut app = app_in(InputMode::Live);
+        app.quality_picker = Some(QualityPicker {
+            room_id: 1,
+            qualities: Vec::new(),
+            list_state: ListState::default(),
+        });
+        assert_keys!(app,
+            Char('j') => Some(Action::SelectNext),
+            Char('k') => Some(Action::SelectPrev),
+            Enter => Some(Action::Confirm),
+            Char('q') => Some(Action::Back),
+            Char(' ') => None,
+            Char('R') => None,
+            Char('p') => None,
+        );
+    }
+
+    #[test]
+    fn searching_shows_the_results_once_they_arrive() {
+        let mut app = app_in(InputMode::Start);
+        press(&mut app, Char('/'));
+        assert_eq!(app.mode, InputMode::Editing);
+        type_text(&mut app, "rust");
+        assert_eq!(app.search_input.value(), "rust");
+
+        press(&mut app, Enter);
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(app.tasks.queued(), [Some("Searching")]);
+        assert_eq!(app.tasks.busy().map(|(label, _)| label), Some("Searching"));
+
+        show(&mut app, 2, ResultsSource::Search);
+        assert_eq!(app.mode, InputMode::ListNav);
+        assert_eq!(app.focused_panel, Focusable::Results);
+        assert_eq!(app.search_results.len(), 2);
+        assert_eq!(app.results_list_state.selected(), Some(0));
+    }
+
+    #[test]
+    fn navigates_the_results_list_and_leaves_it() {
+        let mut app = App::new();
+        show(&mut app, 3, ResultsSource::Search);
+        press(&mut app, Char('j'));
+        press(&mut app, Char('j'));
+        assert_eq!(app.results_list_state.selected(), Some(2));
+        press(&mut app, Char('j'));
+        assert_eq!(app.results_list_state.selected(), Some(0));
+        press(&mut app, Char('k'));
+        assert_eq!(app.results_list_state.selected(), Some(2));
+
+        // Search results have no pages to turn.
+        press(&mut app, Char('l'));
+        assert!(app.tasks.queued().is_empty());
+
+        press(&mut app, Esc);
+        assert_eq!(app.mode, InputMode::Normal);
+        assert_eq!(app.focused_panel, Focusable::None);
+        assert_eq!(app.results_list_state.selected(), None);
+    }
+
+    #[test]
+    fn queues_playback_instead_of_starting_it() {
+        let mut app = App::new();
+        show(&mut app, 2, ResultsSource::Search);
+        press(&mut app, Char('P'));
+        assert_eq!(
+            app.effects,
+            [Effect::Play {
+                args: vec![
+                    "https://www.bilibili.com/video/BV1".to_string(),
+                    "https://www.bilibili.com/video/BV2".to_string(),
+                ],
+                what: "videos",
+            }]
+        );
+    }
+
+    #[test]
+    fn opens_details_and_returns_to_the_list() {
+        let mut app = App::new();
+        show(
+            &mut app,
+            3,
+            ResultsSource::Popular {
+                page: 1,
+                no_more: false,
+            },
+        );
+        press(&mut app, Char('j'));
+        press(&mut app, Enter);
+        assert_eq!(app.mode, InputMode::Detail);
+        assert_eq!(app.detail_loading.as_deref(), Some("BV2"));
+
+        // In the detail view `l` likes rather than turning the page.
+        press(&mut app, Char('l'));
+        assert_eq!(
+            app.last_error.as_deref(),
+            Some("The video's details are still loading")
+        );
+
+        app.update(Action::TaskFinished(Ok(AppEvent::Details {
+            bvid: "BV2".to_string(),
+            result: Err("Video not found".to_string()),
+        })));
+        assert_eq!(app.detail_loading, None);
+        assert_eq!(app.last_error.as_deref(), Some("Video not found"));
+
+        press(&mut app, Char('q'));
+        assert_eq!(app.mode, InputMode::ListNav);
+        assert_eq!(app.results_list_state.selected(), Some(1));
+    }
+
+    #[test]
+    fn moves_focus_in_the_detail_view_unless_browsing_related_videos() {
+        let mut app = App::new();
+        show(&mut app, 1, ResultsSource::Search);
+        press(&mut app, Enter);
+        assert_eq!(app.mode, InputMode::Detail);
+
+        // The related list keeps `j`/`k` while it has the focus.
+        press(&mut app, Char('j'));
+        assert_eq!(app.focused_panel, Focusable::Results);
+        press(&mut app, Tab);
+        assert_eq!(app.focused_panel, Focusable::Command);
+        press(&mut app, Char('j'));
+        assert_eq!(app.focused_panel, Focusable::Search);
+        press(&mut app, Char('k'));
+        assert_eq!(app.focused_panel, Focusable::Command);
+        press(&mut app, BackTab);
+        assert_eq!(app.focused_panel, Focusable::Results);
+    }
+
+    #[test]
+    fn runs_commands_typed_into_the_command_line() {
+        let mut app = App::new();
+        press(&mut app, Char(':'));
+        assert_eq!(app.mode, InputMode::Command);
+        type_text(&mut app, "help");
+        press(&mut app, Enter);
+        assert_eq!(app.mode, InputMode::Help);
+        press(&mut app, Char('x'));
+        assert_eq!(app.mode, InputMode::Normal);
+
+        // The command line keeps the focus, so `:` opens it again.
+        press(&mut app, Char(':'));
+        type_text(&mut app, "offline on");
+        press(&mut app, Enter);
+        assert_eq!(app.effects, [Effect::SetOffline(Some(true))]);
+
+        press(&mut app, Char(':'));
+        type_text(&mut app, "nope");
+        press(&mut app, Enter);
+        assert!(
+            app.last_error
+                .as_ref()
+                .is_some_and(|e| e.starts_with("Unknown command"))
+        );
+
+        // `q` leaves the command line first, and quits only from outside it.
+        press(&mut app, Char(':'));
+        press(&mut app, Char('q'));
+        assert_eq!(app.mode, InputMode::Normal);
+        assert!(!app.should_quit);
+        press(&mut app, Char('q'));
+        assert!(app.should_quit);
+    }
+}
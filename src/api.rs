@@ -1,24 +1,76 @@
-use serde::Deserialize;
-
-#[derive(Deserialize, Debug)]
-struct VideoInfoResponse {
-    data: VideoInfo,
-}
+use crate::cache;
+use crate::scheduler;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, de::DeserializeOwned};
+use serde_json::Value;
+use std::time::Duration;
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct VideoInfo {
+    pub aid: u64,
     pub bvid: String,
     pub title: String,
+    #[serde(default)]
     pub desc: String,
+    /// Cover image URL.
+    #[serde(default)]
+    pub pic: String,
+    /// Publish time as a Unix timestamp.
+    #[serde(default)]
+    pub pubdate: i64,
+    /// Total length in seconds.
+    #[serde(default)]
+    pub duration: u64,
+    /// Category name, e.g. 单机游戏.
+    #[serde(default)]
+    pub tname: String,
+    /// 1 for original work, 2 for reposts.
+    #[serde(default)]
+    pub copyright: u8,
     pub owner: Owner,
     pub stat: Stat,
+    /// Co-creators of a joint submission; absent for single-uploader videos.
+    #[serde(default)]
+    pub staff: Vec<Staff>,
+    /// Filled in from the tag endpoint, which the view endpoint doesn't cover.
+    #[serde(skip)]
+    pub tags: Vec<Tag>,
+}
+
+impl VideoInfo {
+    pub fn is_original(&self) -> bool {
+        self.copyright == 1
+    }
+
+    /// Coins a user may give the video: two for original work, one for a repost.
+    pub fn max_coins(&self) -> u8 {
+        if self.is_original() { 2 } else { 1 }
+    }
+}
+
+/// A co-creator of a joint submission and their role in it.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Staff {
+    /// The role, e.g. UP主 or 剪辑.
+    pub title: String,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Tag {
+    pub tag_name: String,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Clone)]
 pub struct Owner {
+    pub mid: u64,
     pub name: String,
+    #[serde(default)]
+    pub face: String,
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Clone)]
 pub struct Stat {
     pub view: u64,
@@ -26,62 +78,459 @@ pub struct Stat {
     pub coin: u64,
     pub favorite: u64,
     pub share: u64,
-}
-
-#[derive(Deserialize, Debug)]
-struct SearchResponse {
-    data: SearchData,
+    #[serde(default)]
+    pub danmaku: u64,
+    #[serde(default)]
+    pub reply: u64,
 }
 
 #[derive(Deserialize, Debug)]
 struct SearchData {
     result: Option<Vec<VideoResult>>,
+    #[serde(default = "default_num_pages", rename = "numPages")]
+    num_pages: u32,
+}
+
+fn default_num_pages() -> u32 {
+    1
 }
 
+#[allow(dead_code)]
 #[derive(Deserialize, Debug, Clone)]
 pub struct VideoResult {
     pub r#type: String,
+    #[serde(default)]
+    pub aid: u64,
     pub author: String,
+    /// The uploader's id.
+    #[serde(default)]
+    pub mid: u64,
     pub bvid: String,
-    #[serde(deserialize_with = "strip_em_tags")]
-    pub title: String,
-    pub description: String,
-    pub play: serde_json::Value,
+    #[serde(deserialize_with = "highlighted")]
+    pub title: Highlighted,
+    #[serde(deserialize_with = "highlighted")]
+    pub description: Highlighted,
+    /// Play count; `None` where the API hides it behind "--".
+    #[serde(default, deserialize_with = "lenient_count")]
+    pub play: Option<u64>,
+    #[serde(default, deserialize_with = "lenient_number")]
     pub like: u64,
+    #[serde(default, deserialize_with = "lenient_number")]
     pub video_review: u64,
-    pub duration: String,
+    #[serde(default, deserialize_with = "lenient_duration")]
+    pub duration: Duration,
+    #[serde(default, deserialize_with = "lenient_timestamp")]
+    pub pubdate: Option<DateTime<Utc>>,
+    /// Cover image URL, always with a scheme.
+    #[serde(default, deserialize_with = "cover_url")]
+    pub pic: String,
+    #[serde(default, rename = "tag", deserialize_with = "lenient_tags")]
+    pub tags: Vec<String>,
+}
+
+impl VideoResult {
+    /// Whether the video is tagged `tag`, ignoring case.
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags
+            .iter()
+            .any(|t| t.to_lowercase() == tag.to_lowercase())
+    }
 }
 
-fn strip_em_tags<'de, D>(deserializer: D) -> Result<String, D::Error>
+/// Reads a count given as a number, a numeric string or an abbreviation like "1.5万", treating
+/// placeholders such as "--" or an empty string as unknown.
+fn lenient_count<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
-    let s = String::deserialize(deserializer)?;
-    Ok(s.replace("<em class=\"keyword\">", "").replace("</em>", ""))
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64().or_else(|| n.as_f64().map(|f| f.max(0.0) as u64)),
+        Value::String(s) => parse_count(&s),
+        _ => None,
+    })
 }
 
-pub async fn search(keyword: &str) -> Result<Vec<VideoResult>, Box<dyn std::error::Error + Send + Sync>> {
-    let url = format!(
-        "https://api.bilibili.com/x/web-interface/search/type?search_type=video&keyword={}",
-        keyword
-    );
+fn parse_count(s: &str) -> Option<u64> {
+    let s = s.trim();
+    let (number, multiplier) = if let Some(number) = s.strip_suffix('万') {
+        (number, 1e4)
+    } else if let Some(number) = s.strip_suffix('亿') {
+        (number, 1e8)
+    } else {
+        (s, 1.0)
+    };
+    let value: f64 = number.parse().ok()?;
+    (value >= 0.0).then(|| (value * multiplier).round() as u64)
+}
+
+/// Like [`lenient_count`], with unknown counts read as 0.
+fn lenient_number<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(lenient_count(deserializer)?.unwrap_or_default())
+}
+
+/// Reads a duration given as seconds or as "m:ss"/"h:mm:ss", with anything else read as zero.
+fn lenient_duration<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let secs = match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_u64(),
+        Value::String(s) => s.split(':').try_fold(0u64, |total, part| {
+            part.trim()
+                .parse::<u64>()
+                .ok()
+                .map(|part| total * 60 + part)
+        }),
+        _ => None,
+    };
+    Ok(Duration::from_secs(secs.unwrap_or_default()))
+}
+
+/// Reads a Unix timestamp, with zero, negative or missing values read as unknown.
+fn lenient_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let secs = match Value::deserialize(deserializer)? {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    };
+    Ok(secs.and_then(publish_date))
+}
+
+/// A publish date from a Unix timestamp, with zero or negative values read as unknown.
+fn publish_date(secs: i64) -> Option<DateTime<Utc>> {
+    (secs > 0)
+        .then(|| DateTime::from_timestamp(secs, 0))
+        .flatten()
+}
+
+/// Reads a cover image URL, adding the scheme search results leave out and treating null as
+/// no cover.
+fn cover_url<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let url = Option::<String>::deserialize(deserializer)?;
+    Ok(with_scheme(url.unwrap_or_default()))
+}
+
+/// `url` with `https:` in front if it starts with `//`.
+fn with_scheme(url: String) -> String {
+    match url.strip_prefix("//") {
+        Some(rest) => format!("https://{}", rest),
+        None => url,
+    }
+}
+
+/// Reads tags given either as a comma-separated string or as a list.
+fn lenient_tags<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let tags = match Value::deserialize(deserializer)? {
+        Value::String(s) => s.split(',').map(|tag| tag.trim().to_string()).collect(),
+        Value::Array(tags) => tags
+            .into_iter()
+            .filter_map(|tag| tag.as_str().map(|tag| tag.trim().to_string()))
+            .collect(),
+        _ => Vec::new(),
+    };
+    Ok(tags
+        .into_iter()
+        .filter(|tag: &String| !tag.is_empty())
+        .collect())
+}
+
+/// Search text with the ranges the query matched, which the API marks with `<em class="keyword">`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Highlighted {
+    /// The text without markup and with HTML entities decoded.
+    pub text: String,
+    /// Byte ranges of `text` that matched, in order.
+    pub matches: Vec<std::ops::Range<usize>>,
+}
+
+impl Highlighted {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            matches: Vec::new(),
+        }
+    }
+
+    /// Parses search markup, keeping `<em>` ranges as matches and dropping any other tags.
+    pub fn parse(markup: &str) -> Self {
+        let mut highlighted = Self::default();
+        let mut rest = markup;
+        let mut in_match = false;
+        while let Some(start) = rest.find('<') {
+            highlighted.push(&rest[..start], in_match);
+            let Some(end) = rest[start..].find('>') else {
+                // A stray '<' rather than a tag.
+                highlighted.push(&rest[start..], in_match);
+                return highlighted;
+            };
+            let tag = &rest[start + 1..start + end];
+            if tag == "em" || tag.starts_with("em ") {
+                in_match = true;
+            } else if tag == "/em" {
+                in_match = false;
+            }
+            rest = &rest[start + end + 1..];
+        }
+        highlighted.push(rest, in_match);
+        highlighted
+    }
+
+    /// Marks every occurrence of `words` in `text`, e.g. to carry a search's matches over to
+    /// the full title shown in the detail view.
+    pub fn with_words<'a>(text: &str, words: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut matches: Vec<std::ops::Range<usize>> = words
+            .into_iter()
+            .filter(|word| !word.is_empty())
+            .flat_map(|word| text.match_indices(word).map(|(i, word)| i..i + word.len()))
+            .collect();
+        matches.sort_by_key(|range| range.start);
+        // Overlapping words would split a range twice, so merge them.
+        let mut merged: Vec<std::ops::Range<usize>> = Vec::new();
+        for range in matches {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
+        }
+        Self {
+            text: text.to_string(),
+            matches: merged,
+        }
+    }
+
+    /// The distinct matched words.
+    pub fn matched_words(&self) -> Vec<&str> {
+        let mut words: Vec<&str> = self
+            .matches
+            .iter()
+            .map(|range| &self.text[range.clone()])
+            .collect();
+        words.sort_unstable();
+        words.dedup();
+        words
+    }
+
+    fn push(&mut self, markup: &str, matched: bool) {
+        let start = self.text.len();
+        self.text.push_str(&decode_entities(markup));
+        let end = self.text.len();
+        if !matched || start == end {
+            return;
+        }
+        match self.matches.last_mut() {
+            Some(last) if last.end == start => last.end = end,
+            _ => self.matches.push(start..end),
+        }
+    }
+}
+
+impl std::fmt::Display for Highlighted {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl From<String> for Highlighted {
+    fn from(text: String) -> Self {
+        Self::plain(text)
+    }
+}
+
+/// Decodes the HTML entities search results leak, named and numeric; unknown ones are kept as is.
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest
+            .find(';')
+            .filter(|&end| end <= 10)
+            .and_then(|end| decode_entity(&rest[1..end]).map(|c| (c, end)));
+        match entity {
+            Some((c, end)) => {
+                decoded.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+fn decode_entity(name: &str) -> Option<char> {
+    if let Some(number) = name.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    Some(match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        "nbsp" => '\u{a0}',
+        _ => return None,
+    })
+}
+
+fn highlighted<'de, D>(deserializer: D) -> Result<Highlighted, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Highlighted::parse(&String::deserialize(deserializer)?))
+}
+
+type ApiResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+pub const USER_AGENT: &str = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36";
+
+#[derive(Deserialize, Debug)]
+struct ApiResponse<T> {
+    code: i64,
+    #[serde(default)]
+    message: String,
+    /// PGC endpoints name the payload `result` instead of `data`.
+    #[serde(alias = "result")]
+    data: Option<T>,
+}
+
+/// Performs a GET against the Bilibili API and decodes the `{code, message, data}` envelope,
+/// answering from the response cache where its policy allows.
+async fn get_response<T: DeserializeOwned>(url: &str) -> ApiResult<ApiResponse<T>> {
     let cookie = std::env::var("BILI_COOKIE").unwrap_or_else(|_| "".to_string());
-    let client = reqwest::Client::builder().user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36").build()?;
-    let response = client.get(&url).header("Cookie", cookie).send().await?;
+    match cache::lookup(url, &cookie) {
+        cache::Lookup::Fresh(body) => return decode_body(&body),
+        cache::Lookup::Stale(body) => {
+            let key = cache::key(url, &cookie);
+            if cache::start_refresh(&key) {
+                let url = url.to_string();
+                tokio::spawn(async move {
+                    // A failed refresh leaves the stale entry for the next attempt.
+                    let _ = fetch_body(&url, &cookie).await;
+                    cache::finish_refresh(&key);
+                });
+            }
+            return decode_body(&body);
+        }
+        cache::Lookup::Miss => {}
+    }
+    ensure_online()?;
+    decode_body(&fetch_body(url, &cookie).await?)
+}
+
+/// GETs `url` from the network, storing the body in the cache if the API accepted the request.
+async fn fetch_body(url: &str, cookie: &str) -> ApiResult<String> {
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    let body = scheduler::send(url, true, || client.get(url).header("Cookie", cookie)).await?;
+    if cacheable(url, &body) {
+        cache::store(url, cookie, &body);
+    }
+    Ok(body)
+}
+
+/// Whether `body` is an answer worth keeping: one the API accepted, or the nav endpoint's
+/// "not logged in", which still carries the WBI keys. Anywhere else, "not logged in" must not
+/// outlive a login.
+fn cacheable(url: &str, body: &str) -> bool {
+    let envelope: Option<ApiResponse<serde::de::IgnoredAny>> = serde_json::from_str(body).ok();
+    envelope.is_some_and(|envelope| envelope.code == 0 || (envelope.code == -101 && url == NAV_URL))
+}
+
+/// Fails with an explanation when offline mode forbids going to the network.
+fn ensure_online() -> ApiResult<()> {
+    if cache::is_offline() {
+        return Err("Offline: this isn't cached yet (:offline off to go online)".into());
+    }
+    Ok(())
+}
 
-    let body_text = response.text().await?;
-    let response = match serde_json::from_str::<SearchResponse>(&body_text) {
-        Ok(parsed) => parsed,
+fn decode_body<T: DeserializeOwned>(body_text: &str) -> ApiResult<ApiResponse<T>> {
+    match serde_json::from_str(body_text) {
+        Ok(parsed) => Ok(parsed),
         Err(e) => {
-            return Err(format!(
-                "error decoding response body: {e}. Raw response: {body_text}"
-            )
-            .into());
+            Err(format!("error decoding response body: {e}. Raw response: {body_text}").into())
         }
-    };
+    }
+}
+
+/// Like [`get_response`], but fails on a non-zero `code` and returns only the `data` payload.
+async fn get_data<T: DeserializeOwned>(url: &str) -> ApiResult<T> {
+    let response: ApiResponse<T> = get_response(url).await?;
+    if response.code != 0 {
+        return Err(format!("API error {}: {}", response.code, response.message).into());
+    }
+    response
+        .data
+        .ok_or_else(|| "API response contained no data".into())
+}
+
+/// The `bili_jct` CSRF token from `BILI_COOKIE`, required by every write endpoint.
+fn csrf_token() -> ApiResult<String> {
+    let cookie = std::env::var("BILI_COOKIE").unwrap_or_default();
+    cookie
+        .split(';')
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == "bili_jct")
+        .map(|(_, value)| value.to_string())
+        .ok_or_else(|| "BILI_COOKIE has no bili_jct token; log in to use this action".into())
+}
+
+/// POSTs a CSRF-signed form to the Bilibili API and unwraps the response envelope.
+async fn post_form<T: DeserializeOwned>(
+    url: &str,
+    params: &[(&str, String)],
+) -> ApiResult<Option<T>> {
+    ensure_online()?;
+    let cookie = std::env::var("BILI_COOKIE").unwrap_or_else(|_| "".to_string());
+    let mut form = params.to_vec();
+    form.push(("csrf", csrf_token()?));
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    // Not retried: a write that timed out may still have gone through.
+    let body = scheduler::send(url, false, || {
+        client.post(url).header("Cookie", &cookie).form(&form)
+    })
+    .await?;
+
+    let response: ApiResponse<T> = decode_body(&body)?;
+    if response.code != 0 {
+        return Err(format!("API error {}: {}", response.code, response.message).into());
+    }
+    Ok(response.data)
+}
+
+/// The video search URL for `keyword`, which may contain spaces, `&` and the like.
+fn search_url(keyword: &str) -> String {
+    format!(
+        "https://api.bilibili.com/x/web-interface/search/type?search_type=video&keyword={}",
+        encode_component(keyword)
+    )
+}
+
+pub async fn search(keyword: &str) -> ApiResult<Vec<VideoResult>> {
+    let data: SearchData = get_data(&search_url(keyword)).await?;
 
     let mut videos = vec![];
-    if let Some(results) = response.data.result {
+    if let Some(results) = data.result {
         videos = results
             .into_iter()
             .filter(|r| r.r#type == "video")
@@ -90,25 +539,1828 @@ pub async fn search(keyword: &str) -> Result<Vec<VideoResult>, Box<dyn std::erro
     Ok(videos)
 }
 
-pub async fn get_video_info(bvid: &str) -> Result<VideoInfo, Box<dyn std::error::Error + Send + Sync>> {
+/// How many tagged videos make up a page of tag results.
+const TAG_PAGE_SIZE: usize = 20;
+/// Search pages read for one page of tag results at most, however few of them match.
+const TAG_SEARCH_PAGES: u32 = 5;
+/// The search endpoint serves no pages beyond this one.
+const SEARCH_PAGE_LIMIT: u32 = 50;
+
+/// One page of videos tagged with a tag, and where the next one starts.
+#[derive(Debug, Clone)]
+pub struct TagPage {
+    pub videos: Vec<VideoResult>,
+    /// The search page to continue from, or `None` once the search has no more pages.
+    pub next: Option<u32>,
+}
+
+/// The search page after `page`, if the search has one.
+fn next_search_page(page: u32, num_pages: u32) -> Option<u32> {
+    (page < num_pages.min(SEARCH_PAGE_LIMIT)).then_some(page + 1)
+}
+
+/// A page of videos tagged `tag`: a search for the tag's name from search page `start`,
+/// keeping only the results that actually carry the tag rather than merely mentioning it.
+/// Reads further search pages until the page is full, up to [`TAG_SEARCH_PAGES`] of them.
+pub async fn search_tag(tag: &str, start: u32) -> ApiResult<TagPage> {
+    let mut videos = Vec::new();
+    let mut page = start;
+    for _ in 0..TAG_SEARCH_PAGES {
+        let url = format!("{}&page={}", search_url(tag), page);
+        let data: SearchData = get_data(&url).await?;
+        videos.extend(
+            data.result
+                .unwrap_or_default()
+                .into_iter()
+                .filter(|r| r.r#type == "video" && r.has_tag(tag)),
+        );
+        let Some(next) = next_search_page(page, data.num_pages) else {
+            return Ok(TagPage { videos, next: None });
+        };
+        page = next;
+        if videos.len() >= TAG_PAGE_SIZE {
+            break;
+        }
+    }
+    Ok(TagPage {
+        videos,
+        next: Some(page),
+    })
+}
+
+pub async fn get_video_info(bvid: &str) -> ApiResult<VideoInfo> {
     let url = format!(
         "https://api.bilibili.com/x/web-interface/view?bvid={}",
         bvid
     );
-    let cookie = std::env::var("BILI_COOKIE").unwrap_or_else(|_| "".to_string());
-    let client = reqwest::Client::builder().user_agent("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/120.0.0.0 Safari/537.36").build()?;
-    let response = client.get(&url).header("Cookie", cookie).send().await?;
+    get_data(&url).await
+}
 
-    let body_text = response.text().await?;
-    let response: VideoInfoResponse = match serde_json::from_str(&body_text) {
-        Ok(parsed) => parsed,
-        Err(e) => {
-            return Err(format!(
-                "error decoding response body: {e}. Raw response: {body_text}"
-            )
-            .into());
+pub async fn get_video_tags(bvid: &str) -> ApiResult<Vec<Tag>> {
+    let url = format!("https://api.bilibili.com/x/tag/archive/tags?bvid={}", bvid);
+    get_data(&url).await
+}
+
+pub async fn get_related(bvid: &str) -> ApiResult<Vec<VideoInfo>> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/archive/related?bvid={}",
+        bvid
+    );
+    get_data(&url).await
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserCard {
+    pub name: String,
+    #[serde(default)]
+    pub sign: String,
+    pub level_info: LevelInfo,
+    pub attention: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LevelInfo {
+    pub current_level: u32,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct UserProfile {
+    pub card: UserCard,
+    pub follower: u64,
+    #[serde(default)]
+    pub archive_count: u64,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpaceVideo {
+    pub bvid: String,
+    pub title: String,
+    /// Play count; `None` where the API hides it behind "--".
+    #[serde(default, deserialize_with = "lenient_count")]
+    pub play: Option<u64>,
+    pub length: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct SpaceArcData {
+    list: SpaceArcList,
+    page: SpacePage,
+}
+
+#[derive(Deserialize, Debug)]
+struct SpaceArcList {
+    #[serde(default)]
+    vlist: Vec<SpaceVideo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SpacePage {
+    count: u32,
+}
+
+/// One page of an uploader's videos, together with the total number of uploads.
+#[derive(Debug, Clone)]
+pub struct SpaceVideos {
+    pub videos: Vec<SpaceVideo>,
+    pub total: u32,
+}
+
+/// Sort orders accepted by the space arc search endpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpaceOrder {
+    Latest,
+    MostPlayed,
+    MostFavorited,
+}
+
+impl SpaceOrder {
+    fn as_param(self) -> &'static str {
+        match self {
+            Self::Latest => "pubdate",
+            Self::MostPlayed => "click",
+            Self::MostFavorited => "stow",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Latest => "latest",
+            Self::MostPlayed => "most played",
+            Self::MostFavorited => "most favorited",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Self::Latest => Self::MostPlayed,
+            Self::MostPlayed => Self::MostFavorited,
+            Self::MostFavorited => Self::Latest,
         }
+    }
+}
+
+pub async fn get_user_profile(mid: u64) -> ApiResult<UserProfile> {
+    let url = format!("https://api.bilibili.com/x/web-interface/card?mid={}", mid);
+    get_data(&url).await
+}
+
+#[derive(Deserialize, Debug)]
+struct UserSearchData {
+    result: Option<Vec<UserSearchResult>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct UserSearchResult {
+    mid: u64,
+}
+
+/// Resolves an uploader name to a mid using the best match from user search.
+pub async fn find_user(name: &str) -> ApiResult<u64> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/search/type?search_type=bili_user&keyword={}",
+        encode_component(name)
+    );
+    let data: UserSearchData = get_data(&url).await?;
+    data.result
+        .and_then(|users| users.into_iter().next())
+        .map(|user| user.mid)
+        .ok_or_else(|| format!("No uploader named {}", name).into())
+}
+
+pub async fn get_space_videos(mid: u64, page: u32, order: SpaceOrder) -> ApiResult<SpaceVideos> {
+    let params = vec![
+        ("mid", mid.to_string()),
+        ("pn", page.to_string()),
+        ("ps", SPACE_PAGE_SIZE.to_string()),
+        ("order", order.as_param().to_string()),
+    ];
+    let url = format!(
+        "https://api.bilibili.com/x/space/wbi/arc/search?{}",
+        sign_wbi(params).await?
+    );
+    let data: SpaceArcData = get_data(&url).await?;
+    Ok(SpaceVideos {
+        videos: data.list.vlist,
+        total: data.page.count,
+    })
+}
+
+pub const SPACE_PAGE_SIZE: u32 = 30;
+
+const MIXIN_KEY_ENC_TAB: [usize; 64] = [
+    46, 47, 18, 2, 53, 8, 23, 32, 15, 50, 10, 31, 58, 3, 45, 35, 27, 43, 5, 49, 33, 9, 42, 19, 29,
+    28, 14, 39, 12, 38, 41, 13, 37, 48, 7, 16, 24, 55, 40, 61, 26, 17, 0, 1, 60, 51, 30, 4, 22, 25,
+    54, 21, 56, 59, 6, 63, 57, 62, 11, 36, 20, 34, 44, 52,
+];
+
+static WBI_MIXIN_KEY: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+#[derive(Deserialize, Debug)]
+struct NavData {
+    #[serde(default, rename = "isLogin")]
+    is_login: bool,
+    #[serde(default)]
+    mid: u64,
+    wbi_img: WbiImg,
+}
+
+/// Reports the login state and the WBI signing keys.
+const NAV_URL: &str = "https://api.bilibili.com/x/web-interface/nav";
+
+/// The mid of the account `BILI_COOKIE` belongs to.
+pub async fn current_user_mid() -> ApiResult<u64> {
+    let response: ApiResponse<NavData> = get_response(NAV_URL).await?;
+    match response.data {
+        Some(nav) if nav.is_login => Ok(nav.mid),
+        _ => Err("Not logged in: set BILI_COOKIE to use this view".into()),
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct WbiImg {
+    img_url: String,
+    sub_url: String,
+}
+
+/// Fetches (once per session) the key used to sign `/wbi/` endpoints.
+async fn wbi_mixin_key() -> ApiResult<String> {
+    if let Some(key) = WBI_MIXIN_KEY.lock().unwrap().clone() {
+        return Ok(key);
+    }
+
+    // The nav endpoint reports -101 when logged out but still carries the keys.
+    let response: ApiResponse<NavData> = get_response(NAV_URL).await?;
+    let nav = response.data.ok_or("nav response contained no WBI keys")?;
+    let stem = |url: &str| {
+        url.rsplit('/')
+            .next()
+            .and_then(|file| file.split('.').next())
+            .unwrap_or_default()
+            .to_string()
     };
+    let raw = stem(&nav.wbi_img.img_url) + &stem(&nav.wbi_img.sub_url);
+    let raw: Vec<char> = raw.chars().collect();
+    let key: String = MIXIN_KEY_ENC_TAB
+        .iter()
+        .filter_map(|&i| raw.get(i))
+        .take(32)
+        .collect();
 
-    Ok(response.data)
+    *WBI_MIXIN_KEY.lock().unwrap() = Some(key.clone());
+    Ok(key)
+}
+
+/// Builds a query string for `params` carrying the `wts` and `w_rid` WBI signature.
+async fn sign_wbi(mut params: Vec<(&str, String)>) -> ApiResult<String> {
+    let mixin_key = wbi_mixin_key().await?;
+    let wts = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)?
+        .as_secs();
+    params.push(("wts", wts.to_string()));
+    params.sort_by(|a, b| a.0.cmp(b.0));
+
+    let query = params
+        .iter()
+        .map(|(k, v)| {
+            let v: String = v.chars().filter(|c| !"!'()*".contains(*c)).collect();
+            format!("{}={}", k, encode_component(&v))
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let w_rid = format!("{:x}", md5::compute(format!("{}{}", query, mixin_key)));
+    Ok(format!("{}&w_rid={}", query, w_rid))
+}
+
+/// Percent-encodes a query value the way JavaScript's `encodeURIComponent` does.
+fn encode_component(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+        .replace("%7E", "~")
+}
+
+/// Formats a length in seconds the way search results do, e.g. `3:07` or `1:02:03`.
+pub fn format_duration(secs: u64) -> String {
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct FavFolder {
+    pub id: u64,
+    pub title: String,
+    pub media_count: u32,
+    /// Whether the video passed as `rid` when listing is in this folder.
+    #[serde(default)]
+    pub fav_state: u8,
+}
+
+#[derive(Deserialize, Debug)]
+struct FavFolderList {
+    list: Option<Vec<FavFolder>>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FavResourceData {
+    medias: Option<Vec<FavMedia>>,
+    has_more: bool,
+}
+
+#[derive(Deserialize, Debug)]
+struct FavMedia {
+    id: u64,
+    bvid: String,
+    title: String,
+    #[serde(default)]
+    intro: String,
+    #[serde(default)]
+    cover: String,
+    duration: u64,
+    #[serde(default)]
+    pubtime: i64,
+    upper: FavUpper,
+    cnt_info: FavCntInfo,
+}
+
+#[derive(Deserialize, Debug)]
+struct FavUpper {
+    #[serde(default)]
+    mid: u64,
+    name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct FavCntInfo {
+    play: u64,
+    danmaku: u64,
+}
+
+impl From<FavMedia> for VideoResult {
+    fn from(media: FavMedia) -> Self {
+        Self {
+            r#type: "video".to_string(),
+            aid: media.id,
+            author: media.upper.name,
+            mid: media.upper.mid,
+            bvid: media.bvid,
+            title: media.title.into(),
+            description: media.intro.into(),
+            play: Some(media.cnt_info.play),
+            like: 0,
+            video_review: media.cnt_info.danmaku,
+            duration: Duration::from_secs(media.duration),
+            pubdate: publish_date(media.pubtime),
+            pic: with_scheme(media.cover),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// One page of videos in a favorites folder.
+#[derive(Debug, Clone)]
+pub struct FavPage {
+    pub videos: Vec<VideoResult>,
+    pub has_more: bool,
+}
+
+pub const FAV_PAGE_SIZE: u32 = 20;
+
+/// Lists the folders created by `mid`; with `aid`, each folder's `fav_state` says whether it holds that video.
+pub async fn get_fav_folders(mid: u64, aid: Option<u64>) -> ApiResult<Vec<FavFolder>> {
+    let mut url = format!(
+        "https://api.bilibili.com/x/v3/fav/folder/created/list-all?up_mid={}",
+        mid
+    );
+    if let Some(aid) = aid {
+        url.push_str(&format!("&rid={}&type=2", aid));
+    }
+    let data: FavFolderList = get_data(&url).await?;
+    Ok(data.list.unwrap_or_default())
+}
+
+/// Lists a page of a favorites folder, optionally filtered by `keyword`.
+pub async fn get_fav_videos(media_id: u64, page: u32, keyword: &str) -> ApiResult<FavPage> {
+    let url = format!(
+        "https://api.bilibili.com/x/v3/fav/resource/list?media_id={}&pn={}&ps={}&keyword={}&order=mtime&type=0&tid=0&platform=web",
+        media_id,
+        page,
+        FAV_PAGE_SIZE,
+        encode_component(keyword)
+    );
+    let data: FavResourceData = get_data(&url).await?;
+    Ok(FavPage {
+        videos: data
+            .medias
+            .unwrap_or_default()
+            .into_iter()
+            .map(VideoResult::from)
+            .collect(),
+        has_more: data.has_more,
+    })
+}
+
+fn join_ids(ids: &[u64]) -> String {
+    ids.iter().map(u64::to_string).collect::<Vec<_>>().join(",")
+}
+
+/// Adds the video `aid` to the `add` folders and removes it from the `del` folders.
+pub async fn deal_favorite(aid: u64, add: &[u64], del: &[u64]) -> ApiResult<()> {
+    let params = [
+        ("rid", aid.to_string()),
+        ("type", "2".to_string()),
+        ("add_media_ids", join_ids(add)),
+        ("del_media_ids", join_ids(del)),
+    ];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v3/fav/resource/deal", &params)
+        .await?;
+    Ok(())
+}
+
+pub async fn create_fav_folder(title: &str) -> ApiResult<FavFolder> {
+    let params = [
+        ("title", title.to_string()),
+        ("intro", String::new()),
+        ("privacy", "0".to_string()),
+    ];
+    post_form("https://api.bilibili.com/x/v3/fav/folder/add", &params)
+        .await?
+        .ok_or_else(|| "API response contained no folder".into())
+}
+
+pub async fn rename_fav_folder(media_id: u64, title: &str) -> ApiResult<()> {
+    let params = [
+        ("media_id", media_id.to_string()),
+        ("title", title.to_string()),
+        ("intro", String::new()),
+        ("privacy", "0".to_string()),
+    ];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v3/fav/folder/edit", &params)
+        .await?;
+    Ok(())
+}
+
+/// Moves (or, with `copy`, copies) the video `aid` from one of `mid`'s folders to another.
+pub async fn transfer_favorite(
+    src: u64,
+    dst: u64,
+    mid: u64,
+    aid: u64,
+    copy: bool,
+) -> ApiResult<()> {
+    let url = if copy {
+        "https://api.bilibili.com/x/v3/fav/resource/copy"
+    } else {
+        "https://api.bilibili.com/x/v3/fav/resource/move"
+    };
+    let params = [
+        ("src_media_id", src.to_string()),
+        ("tar_media_id", dst.to_string()),
+        ("mid", mid.to_string()),
+        ("resources", format!("{}:2", aid)),
+        ("platform", "web".to_string()),
+    ];
+    post_form::<serde_json::Value>(url, &params).await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub title: String,
+    pub author_name: String,
+    /// Unix timestamp of when the video was last watched.
+    pub view_at: i64,
+    /// Seconds watched, or -1 once the video was finished.
+    pub progress: i64,
+    /// Length of the video in seconds.
+    pub duration: i64,
+    pub history: HistoryItem,
+}
+
+impl HistoryEntry {
+    /// The position to resume playback from, if the video was left unfinished.
+    pub fn resume_at(&self) -> Option<i64> {
+        (self.progress > 0 && self.progress < self.duration).then_some(self.progress)
+    }
+
+    /// Fraction of the video watched, treating finished videos as fully watched.
+    pub fn watched_ratio(&self) -> f64 {
+        if self.progress < 0 || self.duration <= 0 {
+            1.0
+        } else {
+            (self.progress as f64 / self.duration as f64).min(1.0)
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct HistoryItem {
+    pub oid: u64,
+    pub bvid: String,
+}
+
+/// Where the next page of history starts; pass it back to [`get_history`].
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub struct HistoryCursor {
+    pub max: u64,
+    pub view_at: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct HistoryData {
+    cursor: HistoryCursor,
+    #[serde(default)]
+    list: Vec<HistoryEntry>,
+}
+
+/// One page of watch history, newest first.
+#[derive(Debug, Clone)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    pub next: HistoryCursor,
+}
+
+pub const HISTORY_PAGE_SIZE: u32 = 20;
+
+/// Lists watched videos starting at `cursor`, or from the most recent when `None`.
+pub async fn get_history(cursor: Option<HistoryCursor>) -> ApiResult<HistoryPage> {
+    let (max, view_at) = cursor.map_or((0, 0), |c| (c.max, c.view_at));
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/history/cursor?max={}&view_at={}&business=archive&ps={}",
+        max, view_at, HISTORY_PAGE_SIZE
+    );
+    let data: HistoryData = get_data(&url).await?;
+    Ok(HistoryPage {
+        entries: data.list,
+        next: data.cursor,
+    })
+}
+
+pub async fn delete_history(aid: u64) -> ApiResult<()> {
+    let params = [("kid", format!("archive_{}", aid))];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v2/history/delete", &params).await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+struct ToViewData {
+    #[serde(default)]
+    list: Option<Vec<ArchiveItem>>,
+}
+
+/// The video shape shared by the Watch Later, popular, weekly and ranking lists.
+#[derive(Deserialize, Debug)]
+struct ArchiveItem {
+    #[serde(alias = "id")]
+    aid: u64,
+    bvid: String,
+    title: String,
+    #[serde(default)]
+    desc: String,
+    #[serde(default)]
+    pic: String,
+    duration: u64,
+    #[serde(default)]
+    pubdate: i64,
+    owner: Owner,
+    stat: ArchiveStat,
+}
+
+#[derive(Deserialize, Debug)]
+struct ArchiveStat {
+    view: u64,
+    like: u64,
+    danmaku: u64,
+}
+
+impl From<ArchiveItem> for VideoResult {
+    fn from(item: ArchiveItem) -> Self {
+        Self {
+            r#type: "video".to_string(),
+            aid: item.aid,
+            author: item.owner.name,
+            mid: item.owner.mid,
+            bvid: item.bvid,
+            title: item.title.into(),
+            description: item.desc.into(),
+            play: Some(item.stat.view),
+            like: item.stat.like,
+            video_review: item.stat.danmaku,
+            duration: Duration::from_secs(item.duration),
+            pubdate: publish_date(item.pubdate),
+            pic: with_scheme(item.pic),
+            tags: Vec::new(),
+        }
+    }
+}
+
+pub async fn get_watch_later() -> ApiResult<Vec<VideoResult>> {
+    let data: ToViewData = get_data("https://api.bilibili.com/x/v2/history/toview").await?;
+    Ok(into_results(data.list.unwrap_or_default()))
+}
+
+pub async fn add_watch_later(bvid: &str) -> ApiResult<()> {
+    let params = [("bvid", bvid.to_string())];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v2/history/toview/add", &params)
+        .await?;
+    Ok(())
+}
+
+/// How the logged-in user has already interacted with a video.
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Relation {
+    #[serde(default)]
+    pub like: bool,
+    /// Coins already given, at most 2.
+    #[serde(default)]
+    pub coin: u8,
+    #[serde(default)]
+    pub favorite: bool,
+}
+
+pub async fn get_relation(aid: u64) -> ApiResult<Relation> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/archive/relation?aid={}",
+        aid
+    );
+    get_data(&url).await
+}
+
+/// Likes the video, or takes the like back when `like` is false.
+pub async fn like_video(aid: u64, like: bool) -> ApiResult<()> {
+    let params = [
+        ("aid", aid.to_string()),
+        ("like", if like { "1" } else { "2" }.to_string()),
+    ];
+    post_form::<serde_json::Value>(
+        "https://api.bilibili.com/x/web-interface/archive/like",
+        &params,
+    )
+    .await?;
+    Ok(())
+}
+
+/// Gives `multiply` coins (1 or 2), also liking the video when `also_like` is set.
+pub async fn add_coin(aid: u64, multiply: u8, also_like: bool) -> ApiResult<()> {
+    let params = [
+        ("aid", aid.to_string()),
+        ("multiply", multiply.to_string()),
+        ("select_like", u8::from(also_like).to_string()),
+    ];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/web-interface/coin/add", &params)
+        .await?;
+    Ok(())
+}
+
+/// What a 一键三连 actually managed to do; each part can fail on its own, e.g. without coins.
+#[derive(Deserialize, Debug, Clone)]
+pub struct TripleResult {
+    pub like: bool,
+    pub coin: bool,
+    pub fav: bool,
+    /// Coins given by this request.
+    #[serde(default)]
+    pub multiply: u8,
+}
+
+/// Likes, coins and favorites the video in one go.
+pub async fn triple(aid: u64) -> ApiResult<TripleResult> {
+    let params = [("aid", aid.to_string())];
+    post_form(
+        "https://api.bilibili.com/x/web-interface/archive/like/triple",
+        &params,
+    )
+    .await?
+    .ok_or_else(|| "API response contained no data".into())
+}
+
+pub async fn remove_watch_later(aid: u64) -> ApiResult<()> {
+    let params = [("aid", aid.to_string())];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v2/history/toview/del", &params)
+        .await?;
+    Ok(())
+}
+
+/// Removes every video that has been watched to the end from Watch Later.
+pub async fn clear_watched_watch_later() -> ApiResult<()> {
+    let params = [("viewed", "true".to_string())];
+    post_form::<serde_json::Value>("https://api.bilibili.com/x/v2/history/toview/del", &params)
+        .await?;
+    Ok(())
+}
+
+/// A video posted by a followed uploader, flattened from the dynamic feed's module tree.
+#[derive(Debug, Clone)]
+pub struct FeedItem {
+    pub bvid: String,
+    pub title: String,
+    pub author: String,
+    /// Unix timestamp of when the video was published.
+    pub pub_ts: i64,
+    /// Play count; `None` where the API hides it behind "--".
+    pub play: Option<u64>,
+    pub duration: Duration,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedData {
+    has_more: bool,
+    #[serde(default)]
+    offset: String,
+    #[serde(default)]
+    items: Vec<FeedDynamic>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedDynamic {
+    modules: FeedModules,
+}
+
+impl FeedDynamic {
+    /// The video this dynamic posted, or `None` for other kinds of posts.
+    fn into_item(self) -> Option<FeedItem> {
+        let author = self.modules.module_author;
+        let archive = self.modules.module_dynamic.major?.archive?;
+        Some(FeedItem {
+            bvid: archive.bvid,
+            title: archive.title,
+            author: author.name,
+            pub_ts: author.pub_ts,
+            play: archive.stat.play,
+            duration: archive.duration_text,
+        })
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedModules {
+    module_author: FeedAuthor,
+    module_dynamic: FeedModuleDynamic,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedAuthor {
+    name: String,
+    pub_ts: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedModuleDynamic {
+    major: Option<FeedMajor>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedMajor {
+    archive: Option<FeedArchive>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedArchive {
+    bvid: String,
+    title: String,
+    #[serde(default, deserialize_with = "lenient_duration")]
+    duration_text: Duration,
+    stat: FeedArchiveStat,
+}
+
+#[derive(Deserialize, Debug)]
+struct FeedArchiveStat {
+    #[serde(default, deserialize_with = "lenient_count")]
+    play: Option<u64>,
+}
+
+/// One page of the followed-uploaders video feed.
+#[derive(Debug, Clone)]
+pub struct FeedPage {
+    pub items: Vec<FeedItem>,
+    pub has_more: bool,
+    /// Pass back to [`get_feed`] to load the following page.
+    pub offset: String,
+}
+
+/// Lists new videos from followed uploaders, newest first, starting after `offset`.
+pub async fn get_feed(offset: &str) -> ApiResult<FeedPage> {
+    let url = format!(
+        "https://api.bilibili.com/x/polymer/web-dynamic/v1/feed/all?type=video&offset={}",
+        encode_component(offset)
+    );
+    let data: FeedData = get_data(&url).await?;
+    Ok(FeedPage {
+        items: data
+            .items
+            .into_iter()
+            .filter_map(FeedDynamic::into_item)
+            .collect(),
+        has_more: data.has_more,
+        offset: data.offset,
+    })
+}
+
+fn into_results(items: Vec<ArchiveItem>) -> Vec<VideoResult> {
+    items.into_iter().map(VideoResult::from).collect()
+}
+
+#[derive(Deserialize, Debug)]
+struct PopularData {
+    #[serde(default)]
+    list: Vec<ArchiveItem>,
+    no_more: bool,
+}
+
+/// One page of the popular list.
+#[derive(Debug, Clone)]
+pub struct PopularPage {
+    pub videos: Vec<VideoResult>,
+    pub no_more: bool,
+}
+
+pub const POPULAR_PAGE_SIZE: u32 = 20;
+
+pub async fn get_popular(page: u32) -> ApiResult<PopularPage> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/popular?pn={}&ps={}",
+        page, POPULAR_PAGE_SIZE
+    );
+    let data: PopularData = get_data(&url).await?;
+    Ok(PopularPage {
+        videos: into_results(data.list),
+        no_more: data.no_more,
+    })
+}
+
+/// An issue of the weekly "每周必看" series.
+#[derive(Deserialize, Debug, Clone)]
+pub struct WeeklyIssue {
+    pub number: u32,
+    pub subject: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct WeeklyListData {
+    list: Vec<WeeklyIssue>,
+}
+
+#[derive(Deserialize, Debug)]
+struct WeeklyOneData {
+    config: WeeklyIssue,
+    #[serde(default)]
+    list: Vec<ArchiveItem>,
+}
+
+/// The number of the most recent weekly issue.
+pub async fn get_latest_weekly_number() -> ApiResult<u32> {
+    let data: WeeklyListData =
+        get_data("https://api.bilibili.com/x/web-interface/popular/series/list").await?;
+    data.list
+        .iter()
+        .map(|issue| issue.number)
+        .max()
+        .ok_or_else(|| "No weekly issues found".into())
+}
+
+pub async fn get_weekly(number: u32) -> ApiResult<(WeeklyIssue, Vec<VideoResult>)> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/popular/series/one?number={}",
+        number
+    );
+    let data: WeeklyOneData = get_data(&url).await?;
+    Ok((data.config, into_results(data.list)))
+}
+
+/// A ranking board tab: a zone (`rid`) or special board (`kind`) of `/ranking/v2`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RankZone {
+    pub name: &'static str,
+    pub label: &'static str,
+    pub rid: u32,
+    pub kind: &'static str,
+}
+
+const fn zone(name: &'static str, label: &'static str, rid: u32) -> RankZone {
+    RankZone {
+        name,
+        label,
+        rid,
+        kind: "all",
+    }
+}
+
+pub const RANK_ZONES: [RankZone; 18] = [
+    zone("all", "全站", 0),
+    zone("anime", "动画", 1),
+    zone("music", "音乐", 3),
+    zone("dance", "舞蹈", 129),
+    zone("game", "游戏", 4),
+    zone("knowledge", "知识", 36),
+    zone("tech", "科技", 188),
+    zone("sports", "运动", 234),
+    zone("car", "汽车", 223),
+    zone("life", "生活", 160),
+    zone("food", "美食", 211),
+    zone("animal", "动物圈", 217),
+    zone("kichiku", "鬼畜", 119),
+    zone("fashion", "时尚", 155),
+    zone("ent", "娱乐", 5),
+    zone("cinephile", "影视", 181),
+    RankZone {
+        name: "origin",
+        label: "原创",
+        rid: 0,
+        kind: "origin",
+    },
+    RankZone {
+        name: "rookie",
+        label: "新人",
+        rid: 0,
+        kind: "rookie",
+    },
+];
+
+/// Finds a ranking zone by English name, Chinese label or `rid`.
+pub fn find_rank_zone(query: &str) -> Option<usize> {
+    RANK_ZONES.iter().position(|zone| {
+        zone.name.eq_ignore_ascii_case(query)
+            || zone.label == query
+            || (zone.kind == "all" && query.parse() == Ok(zone.rid))
+    })
+}
+
+#[derive(Deserialize, Debug)]
+struct RankingData {
+    #[serde(default)]
+    list: Vec<ArchiveItem>,
+}
+
+pub async fn get_ranking(zone: RankZone) -> ApiResult<Vec<VideoResult>> {
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/ranking/v2?rid={}&type={}",
+        zone.rid, zone.kind
+    );
+    let data: RankingData = get_data(&url).await?;
+    Ok(into_results(data.list))
+}
+
+#[derive(Deserialize, Debug)]
+struct RcmdData {
+    #[serde(default)]
+    item: Vec<RcmdItem>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RcmdItem {
+    #[serde(default)]
+    goto: String,
+    #[serde(default)]
+    track_id: String,
+    #[serde(flatten)]
+    archive: Option<ArchiveItem>,
+}
+
+impl RcmdItem {
+    /// The recommended video, or `None` for ads, live rooms and other non-video cards.
+    fn into_recommendation(self) -> Option<(VideoResult, DislikeTarget)> {
+        if self.goto != "av" {
+            return None;
+        }
+        let archive = self.archive?;
+        let target = DislikeTarget {
+            aid: archive.aid,
+            mid: archive.owner.mid,
+            track_id: self.track_id,
+        };
+        Some((VideoResult::from(archive), target))
+    }
+}
+
+/// What the dislike endpoint needs to know about a recommended video.
+#[derive(Debug, Clone)]
+pub struct DislikeTarget {
+    pub aid: u64,
+    pub mid: u64,
+    pub track_id: String,
+}
+
+/// Why a recommendation is unwanted, as reported to the dislike endpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DislikeReason {
+    Video,
+    Uploader,
+}
+
+impl DislikeReason {
+    fn id(self) -> u32 {
+        match self {
+            Self::Video => 1,
+            Self::Uploader => 4,
+        }
+    }
+}
+
+pub const RCMD_PAGE_SIZE: u32 = 12;
+
+/// Fetches the `fresh_idx`-th batch of the logged-in homepage recommendations.
+pub async fn get_recommendations(fresh_idx: u32) -> ApiResult<Vec<(VideoResult, DislikeTarget)>> {
+    let params = vec![
+        ("fresh_type", "4".to_string()),
+        ("ps", RCMD_PAGE_SIZE.to_string()),
+        ("fresh_idx", fresh_idx.to_string()),
+        ("fresh_idx_1h", fresh_idx.to_string()),
+        ("brush", fresh_idx.to_string()),
+        ("feed_version", "V8".to_string()),
+    ];
+    let url = format!(
+        "https://api.bilibili.com/x/web-interface/wbi/index/top/feed/rcmd?{}",
+        sign_wbi(params).await?
+    );
+    let data: RcmdData = get_data(&url).await?;
+    Ok(data
+        .item
+        .into_iter()
+        .filter_map(RcmdItem::into_recommendation)
+        .collect())
+}
+
+/// Marks a recommendation as unwanted so the feed stops suggesting similar videos.
+pub async fn dislike_recommendation(
+    target: &DislikeTarget,
+    reason: DislikeReason,
+) -> ApiResult<()> {
+    let params = [
+        ("app_id", "100".to_string()),
+        ("goto", "av".to_string()),
+        ("id", target.aid.to_string()),
+        ("mid", target.mid.to_string()),
+        ("reason_id", reason.id().to_string()),
+        ("track_id", target.track_id.clone()),
+        ("feedback_page", "1".to_string()),
+        ("spmid", "333.1007.0.0".to_string()),
+    ];
+    post_form::<serde_json::Value>(
+        "https://api.bilibili.com/x/web-interface/feedback/dislike",
+        &params,
+    )
+    .await?;
+    Ok(())
+}
+
+/// A live room as listed by the area, following and room info endpoints.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LiveRoom {
+    #[serde(alias = "roomid")]
+    pub room_id: u64,
+    #[serde(default)]
+    pub uname: String,
+    pub title: String,
+    #[serde(default)]
+    pub online: u64,
+    #[serde(default)]
+    pub area_name: String,
+    /// 1 while the room is broadcasting.
+    #[serde(default = "default_live_status")]
+    pub live_status: u8,
+}
+
+fn default_live_status() -> u8 {
+    1
+}
+
+/// A top-level live area such as 网游 or 虚拟主播.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LiveArea {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiveRoomList {
+    #[serde(default)]
+    list: Vec<LiveRoom>,
+}
+
+#[derive(Deserialize, Debug)]
+struct FollowedLiveData {
+    #[serde(default)]
+    rooms: Vec<LiveRoom>,
+}
+
+/// A stream quality offered by the live playurl endpoint.
+#[derive(Deserialize, Debug, Clone)]
+pub struct LiveQuality {
+    pub qn: u32,
+    pub desc: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct LivePlayUrlData {
+    #[serde(default)]
+    quality_description: Vec<LiveQuality>,
+    #[serde(default)]
+    durl: Vec<LiveDurl>,
+}
+
+#[derive(Deserialize, Debug)]
+struct LiveDurl {
+    url: String,
+}
+
+/// A resolved live stream: the available qualities and the URL for the requested one.
+#[derive(Debug, Clone)]
+pub struct LivePlayUrl {
+    pub qualities: Vec<LiveQuality>,
+    pub url: String,
+}
+
+/// Referer the live CDN requires alongside the user agent when fetching streams.
+pub const LIVE_REFERER: &str = "https://live.bilibili.com/";
+
+pub async fn get_live_room(room_id: u64) -> ApiResult<LiveRoom> {
+    let url = format!(
+        "https://api.live.bilibili.com/room/v1/Room/get_info?room_id={}",
+        room_id
+    );
+    get_data(&url).await
+}
+
+pub async fn get_live_areas() -> ApiResult<Vec<LiveArea>> {
+    get_data("https://api.live.bilibili.com/room/v1/Area/getList").await
+}
+
+/// Lists the most watched rooms currently live in a top-level area.
+pub async fn get_area_live_rooms(parent_area_id: u32, page: u32) -> ApiResult<Vec<LiveRoom>> {
+    let url = format!(
+        "https://api.live.bilibili.com/xlive/web-interface/v1/second/getList?platform=web&parent_area_id={}&area_id=0&sort_type=online&page={}",
+        parent_area_id, page
+    );
+    let data: LiveRoomList = get_data(&url).await?;
+    Ok(data.list)
+}
+
+/// Lists followed uploaders who are live right now.
+pub async fn get_followed_live_rooms() -> ApiResult<Vec<LiveRoom>> {
+    let data: FollowedLiveData = get_data(
+        "https://api.live.bilibili.com/xlive/web-ucenter/v1/xfetter/GetWebList?page=1&page_size=50",
+    )
+    .await?;
+    Ok(data.rooms)
+}
+
+/// Resolves the stream URL of a room at quality `qn` (10000 is original quality).
+pub async fn get_live_play_url(room_id: u64, qn: u32) -> ApiResult<LivePlayUrl> {
+    let url = format!(
+        "https://api.live.bilibili.com/room/v1/Room/playUrl?cid={}&qn={}&platform=web",
+        room_id, qn
+    );
+    let data: LivePlayUrlData = get_data(&url).await?;
+    let url = data
+        .durl
+        .into_iter()
+        .next()
+        .map(|durl| durl.url)
+        .ok_or("The room is not broadcasting")?;
+    Ok(LivePlayUrl {
+        qualities: data.quality_description,
+        url,
+    })
+}
+
+/// Opens a live stream or playlist URL with the headers the live CDN insists on.
+pub async fn open_live_stream(url: &str) -> ApiResult<reqwest::Response> {
+    ensure_online()?;
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    let response = client
+        .get(url)
+        .header("Referer", LIVE_REFERER)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(response)
+}
+
+/// A bangumi or PGC season, addressed by its own id or by one of its episodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeasonId {
+    /// `ss…`: the season itself.
+    Season(u64),
+    /// `ep…`: an episode; the whole season is loaded around it.
+    Episode(u64),
+}
+
+/// An anime, documentary or other PGC season and its episodes.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Season {
+    pub season_id: u64,
+    pub title: String,
+    /// The synopsis.
+    #[serde(default)]
+    pub evaluate: String,
+    #[serde(default)]
+    pub episodes: Vec<Episode>,
+    #[serde(default)]
+    pub rights: SeasonRights,
+    #[serde(default)]
+    pub new_ep: NewEpisode,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SeasonRights {
+    /// Non-zero when the season is limited to certain regions.
+    #[serde(default)]
+    pub area_limit: u8,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct NewEpisode {
+    /// Update status such as "已完结, 全12话" or "连载中, 每周六更新".
+    #[serde(default)]
+    pub desc: String,
+}
+
+/// One episode of a PGC season.
+#[derive(Deserialize, Debug, Clone)]
+pub struct Episode {
+    /// The episode id, as in `ep…` URLs.
+    pub id: u64,
+    pub cid: u64,
+    /// The episode number, or a label such as "正片" for films.
+    pub title: String,
+    #[serde(default)]
+    pub long_title: String,
+    /// Badge text such as "会员" or "限免"; empty for free episodes.
+    #[serde(default)]
+    pub badge: String,
+    /// Length in milliseconds.
+    #[serde(default)]
+    pub duration: u64,
+}
+
+impl Episode {
+    /// "EP 3 title" for numbered episodes, otherwise the label and title as given.
+    pub fn display_title(&self) -> String {
+        let number = if self.title.parse::<u32>().is_ok() {
+            format!("EP {}", self.title)
+        } else {
+            self.title.clone()
+        };
+        if self.long_title.is_empty() {
+            number
+        } else {
+            format!("{} {}", number, self.long_title)
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("https://www.bilibili.com/bangumi/play/ep{}", self.id)
+    }
+}
+
+pub async fn get_season(id: SeasonId) -> ApiResult<Season> {
+    let query = match id {
+        SeasonId::Season(season_id) => format!("season_id={}", season_id),
+        SeasonId::Episode(ep_id) => format!("ep_id={}", ep_id),
+    };
+    let url = format!("https://api.bilibili.com/pgc/view/web/season?{}", query);
+    get_data(&url).await
+}
+
+/// Asks the PGC player whether the current user may watch `episode`, explaining why not if not.
+pub async fn check_episode_playable(episode: &Episode) -> ApiResult<()> {
+    let url = format!(
+        "https://api.bilibili.com/pgc/player/web/playurl?ep_id={}&cid={}&qn=64&fnval=0",
+        episode.id, episode.cid
+    );
+    let response: ApiResponse<serde_json::Value> = get_response(&url).await?;
+    episode_restriction(response.code, &response.message)
+}
+
+/// Explains a PGC player response code, or `Ok` if the episode may be played.
+fn episode_restriction(code: i64, message: &str) -> ApiResult<()> {
+    match code {
+        0 => Ok(()),
+        // Both restrictions share -10403; only the message tells them apart.
+        -10403 if message.contains("地区") => {
+            Err("This episode is not available in your region".into())
+        }
+        -10403 if message.contains("会员") => {
+            Err(format!("This episode requires a 大会员 membership ({})", message).into())
+        }
+        -10403 => Err(format!("This episode is restricted: {}", message).into()),
+        code => Err(format!("API error {}: {}", code, message).into()),
+    }
+}
+
+/// Downloads an image from the Bilibili image CDN.
+pub async fn get_image(url: &str) -> ApiResult<Vec<u8>> {
+    ensure_online()?;
+    let client = reqwest::Client::builder().user_agent(USER_AGENT).build()?;
+    let response = client.get(url).send().await?.error_for_status()?;
+    Ok(response.bytes().await?.to_vec())
+}
+
+/// Where and how to join a live room's chat.
+#[derive(Deserialize, Debug, Clone)]
+pub struct DanmuInfo {
+    pub token: String,
+    pub host_list: Vec<DanmuHost>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct DanmuHost {
+    pub host: String,
+    pub wss_port: u16,
+}
+
+pub async fn get_danmu_info(room_id: u64) -> ApiResult<DanmuInfo> {
+    let params = vec![("id", room_id.to_string()), ("type", "0".to_string())];
+    let url = format!(
+        "https://api.live.bilibili.com/xlive/web-room/v1/index/getDanmuInfo?{}",
+        sign_wbi(params).await?
+    );
+    get_data(&url).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A search result as the search endpoint returns it, with `overrides` merged in.
+    fn search_result(overrides: Value) -> VideoResult {
+        let mut result = serde_json::json!({
+            "type": "video",
+            "aid": 170001,
+            "author": "碧诗",
+            "mid": 2,
+            "bvid": "BV17x411w7KC",
+            "title": "【MV】<em class=\"keyword\">保加利亚</em>妖王",
+            "description": "",
+            "play": 12345,
+            "like": 678,
+            "video_review": 90,
+            "duration": "4:05",
+            "pubdate": 1262275200,
+            "pic": "//i0.hdslb.com/bfs/archive/a.jpg",
+            "tag": "音乐,MV, 鬼畜 ",
+        });
+        result
+            .as_object_mut()
+            .unwrap()
+            .extend(overrides.as_object().unwrap().clone());
+        serde_json::from_value(result).unwrap()
+    }
+
+    #[test]
+    fn reads_typed_search_result_fields() {
+        let video = search_result(serde_json::json!({}));
+        assert_eq!(video.mid, 2);
+        assert_eq!((video.play, video.video_review), (Some(12345), 90));
+        assert_eq!(video.duration, Duration::from_secs(245));
+        assert_eq!(video.pubdate, DateTime::from_timestamp(1262275200, 0));
+        assert_eq!(video.pic, "https://i0.hdslb.com/bfs/archive/a.jpg");
+        assert_eq!(video.tags, ["音乐", "MV", "鬼畜"]);
+
+        let video = search_result(serde_json::json!({"pic": null}));
+        assert_eq!(video.pic, "");
+    }
+
+    #[test]
+    fn matches_whole_tags_ignoring_case() {
+        let video = search_result(serde_json::json!({}));
+        assert!(video.has_tag("mv"));
+        assert!(video.has_tag("鬼畜"));
+        assert!(!video.has_tag("音"));
+    }
+
+    #[test]
+    fn tolerates_placeholder_counts() {
+        let video =
+            search_result(serde_json::json!({"play": "--", "like": "--", "video_review": ""}));
+        assert_eq!(video.play, None);
+        assert_eq!(video.like, 0);
+        assert_eq!(video.video_review, 0);
+    }
+
+    #[test]
+    fn reads_counts_given_as_strings() {
+        assert_eq!(
+            search_result(serde_json::json!({"play": "4096"})).play,
+            Some(4096)
+        );
+        assert_eq!(
+            search_result(serde_json::json!({"play": "1.5万"})).play,
+            Some(15_000)
+        );
+        assert_eq!(
+            search_result(serde_json::json!({"play": "2亿"})).play,
+            Some(200_000_000)
+        );
+        assert_eq!(search_result(serde_json::json!({"play": null})).play, None);
+    }
+
+    #[test]
+    fn reads_video_owners() {
+        let owner: Owner = serde_json::from_value(serde_json::json!({
+            "mid": 2,
+            "name": "碧诗",
+            "face": "https://i0.hdslb.com/bfs/face/a.jpg",
+        }))
+        .unwrap();
+        assert_eq!((owner.mid, owner.name.as_str()), (2, "碧诗"));
+        assert_eq!(owner.face, "https://i0.hdslb.com/bfs/face/a.jpg");
+
+        // Lists that embed the owner may leave the avatar out.
+        let owner: Owner =
+            serde_json::from_value(serde_json::json!({"mid": 2, "name": "碧诗"})).unwrap();
+        assert_eq!(owner.face, "");
+    }
+
+    #[test]
+    fn reads_space_video_play_counts() {
+        let play = |value| {
+            let video: SpaceVideo = serde_json::from_value(serde_json::json!({
+                "bvid": "BV17x411w7KC",
+                "title": "t",
+                "play": value,
+                "length": "04:05",
+            }))
+            .unwrap();
+            video.play
+        };
+        assert_eq!(play(serde_json::json!(2048)), Some(2048));
+        assert_eq!(play(serde_json::json!("--")), None);
+    }
+
+    #[test]
+    fn keeps_only_video_posts_in_the_feed() {
+        let data: FeedData = serde_json::from_value(serde_json::json!({
+            "has_more": true,
+            "offset": "123",
+            "items": [
+                {"modules": {
+                    "module_author": {"name": "碧诗", "pub_ts": 1262275200},
+                    "module_dynamic": {"major": {"archive": {
+                        "bvid": "BV17x411w7KC",
+                        "title": "t",
+                        "duration_text": "4:05",
+                        "stat": {"play": "--"},
+                    }}},
+                }},
+                {"modules": {
+                    "module_author": {"name": "碧诗", "pub_ts": 1262275300},
+                    "module_dynamic": {"major": null},
+                }},
+            ],
+        }))
+        .unwrap();
+        let items: Vec<FeedItem> = data
+            .items
+            .into_iter()
+            .filter_map(FeedDynamic::into_item)
+            .collect();
+        assert_eq!(items.len(), 1);
+        assert_eq!(
+            (items[0].author.as_str(), items[0].pub_ts),
+            ("碧诗", 1262275200)
+        );
+        assert_eq!(items[0].play, None);
+    }
+
+    #[test]
+    fn reads_feed_counts_and_durations() {
+        let archive: FeedArchive = serde_json::from_value(serde_json::json!({
+            "bvid": "BV17x411w7KC",
+            "title": "t",
+            "duration_text": "12:34",
+            "stat": {"play": "1.2万"},
+        }))
+        .unwrap();
+        assert_eq!(archive.duration_text, Duration::from_secs(754));
+        assert_eq!(archive.stat.play, Some(12_000));
+    }
+
+    #[test]
+    fn reads_durations_in_every_shape() {
+        let duration = |value| search_result(serde_json::json!({"duration": value})).duration;
+        assert_eq!(
+            duration(serde_json::json!("1:02:03")),
+            Duration::from_secs(3723)
+        );
+        assert_eq!(duration(serde_json::json!("0:7")), Duration::from_secs(7));
+        assert_eq!(duration(serde_json::json!(95)), Duration::from_secs(95));
+        assert_eq!(duration(serde_json::json!("--")), Duration::ZERO);
+        assert_eq!(duration(serde_json::json!("")), Duration::ZERO);
+    }
+
+    #[test]
+    fn treats_missing_fields_as_unknown() {
+        let video: VideoResult = serde_json::from_value(serde_json::json!({
+            "type": "video",
+            "author": "碧诗",
+            "bvid": "BV17x411w7KC",
+            "title": "t",
+            "description": "",
+            "pubdate": 0,
+            "tag": "",
+        }))
+        .unwrap();
+        assert_eq!(video.play, None);
+        assert_eq!(video.mid, 0);
+        assert_eq!(video.duration, Duration::ZERO);
+        assert_eq!(video.pubdate, None);
+        assert!(video.tags.is_empty());
+    }
+
+    #[test]
+    fn formats_durations_with_hours_only_when_needed() {
+        assert_eq!(format_duration(0), "0:00");
+        assert_eq!(format_duration(245), "4:05");
+        assert_eq!(format_duration(3599), "59:59");
+        assert_eq!(format_duration(3723), "1:02:03");
+    }
+
+    #[test]
+    fn converts_favorited_videos_to_results() {
+        let media: FavMedia = serde_json::from_value(serde_json::json!({
+            "id": 170001,
+            "bvid": "BV17x411w7KC",
+            "title": "t",
+            "duration": 245,
+            "pubtime": 1262275200,
+            "upper": {"mid": 2, "name": "碧诗"},
+            "cover": "http://i0.hdslb.com/bfs/archive/a.jpg",
+            "cnt_info": {"play": 12345, "danmaku": 67},
+        }))
+        .unwrap();
+        let video = VideoResult::from(media);
+        assert_eq!(video.aid, 170001);
+        assert_eq!((video.author.as_str(), video.mid), ("碧诗", 2));
+        assert_eq!((video.play, video.video_review), (Some(12345), 67));
+        assert_eq!(video.duration, Duration::from_secs(245));
+        assert_eq!(video.pubdate, DateTime::from_timestamp(1262275200, 0));
+        assert!(video.description.text.is_empty());
+        assert_eq!(video.pic, "http://i0.hdslb.com/bfs/archive/a.jpg");
+    }
+
+    #[test]
+    fn caches_not_logged_in_only_for_the_nav_endpoint() {
+        let history = "https://api.bilibili.com/x/web-interface/history/cursor";
+        assert!(cacheable(history, r#"{"code":0,"data":{}}"#));
+        assert!(!cacheable(
+            history,
+            r#"{"code":-101,"message":"账号未登录"}"#
+        ));
+        assert!(!cacheable(
+            history,
+            r#"{"code":-352,"message":"风控校验失败"}"#
+        ));
+        assert!(!cacheable(history, "<html>"));
+        assert!(cacheable(
+            NAV_URL,
+            r#"{"code":-101,"data":{"isLogin":false}}"#
+        ));
+    }
+
+    #[test]
+    fn joins_folder_ids_with_commas() {
+        assert_eq!(join_ids(&[]), "");
+        assert_eq!(join_ids(&[7]), "7");
+        assert_eq!(join_ids(&[7, 42, 3]), "7,42,3");
+    }
+
+    fn history_entry(progress: i64, duration: i64) -> HistoryEntry {
+        HistoryEntry {
+            title: "t".to_string(),
+            author_name: "碧诗".to_string(),
+            view_at: 1262275200,
+            progress,
+            duration,
+            history: HistoryItem {
+                oid: 170001,
+                bvid: "BV17x411w7KC".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn resumes_only_unfinished_videos() {
+        assert_eq!(history_entry(0, 245).resume_at(), None);
+        assert_eq!(history_entry(100, 245).resume_at(), Some(100));
+        assert_eq!(history_entry(245, 245).resume_at(), None);
+        assert_eq!(history_entry(-1, 245).resume_at(), None);
+    }
+
+    #[test]
+    fn treats_finished_videos_as_fully_watched() {
+        assert_eq!(history_entry(0, 200).watched_ratio(), 0.0);
+        assert_eq!(history_entry(50, 200).watched_ratio(), 0.25);
+        assert_eq!(history_entry(300, 200).watched_ratio(), 1.0);
+        assert_eq!(history_entry(-1, 200).watched_ratio(), 1.0);
+        assert_eq!(history_entry(10, 0).watched_ratio(), 1.0);
+    }
+
+    #[test]
+    fn reads_watch_later_lists() {
+        let data: ToViewData = serde_json::from_value(serde_json::json!({
+            "list": [{
+                "aid": 170001,
+                "bvid": "BV17x411w7KC",
+                "title": "t",
+                "pic": "//i1.hdslb.com/bfs/archive/b.jpg",
+                "duration": 245,
+                "owner": {"mid": 2, "name": "碧诗"},
+                "stat": {"view": 12345, "like": 678, "danmaku": 9},
+            }],
+        }))
+        .unwrap();
+        let videos = into_results(data.list.unwrap_or_default());
+        assert_eq!(videos.len(), 1);
+        assert_eq!(
+            (
+                videos[0].aid,
+                videos[0].play,
+                videos[0].like,
+                videos[0].video_review
+            ),
+            (170001, Some(12345), 678, 9)
+        );
+        assert_eq!(videos[0].pubdate, None);
+        assert_eq!(videos[0].pic, "https://i1.hdslb.com/bfs/archive/b.jpg");
+
+        // An empty list comes back as null.
+        let data: ToViewData = serde_json::from_value(serde_json::json!({"list": null})).unwrap();
+        assert!(data.list.is_none());
+    }
+
+    #[test]
+    fn finds_rank_zones_by_name_label_or_rid() {
+        assert_eq!(find_rank_zone("music"), Some(2));
+        assert_eq!(find_rank_zone("MUSIC"), Some(2));
+        assert_eq!(find_rank_zone("音乐"), Some(2));
+        assert_eq!(find_rank_zone("3"), Some(2));
+        assert_eq!(find_rank_zone("rookie"), Some(17));
+        // The special boards share rid 0 with the site-wide board, which wins.
+        assert_eq!(find_rank_zone("0"), Some(0));
+        assert_eq!(find_rank_zone("999"), None);
+        assert_eq!(find_rank_zone("nope"), None);
+    }
+
+    #[test]
+    fn keeps_only_video_recommendations() {
+        let data: RcmdData = serde_json::from_value(serde_json::json!({
+            "item": [
+                {
+                    "goto": "av",
+                    "track_id": "track",
+                    "id": 170001,
+                    "bvid": "BV17x411w7KC",
+                    "title": "t",
+                    "duration": 245,
+                    "owner": {"mid": 2, "name": "碧诗"},
+                    "stat": {"view": 12345, "like": 678, "danmaku": 9},
+                },
+                {"goto": "ad", "track_id": "ad"},
+                {"goto": "live", "track_id": "live", "id": 1},
+            ],
+        }))
+        .unwrap();
+        let recommendations: Vec<_> = data
+            .item
+            .into_iter()
+            .filter_map(RcmdItem::into_recommendation)
+            .collect();
+        assert_eq!(recommendations.len(), 1);
+        let (video, target) = &recommendations[0];
+        assert_eq!(video.bvid, "BV17x411w7KC");
+        assert_eq!(
+            (target.aid, target.mid, target.track_id.as_str()),
+            (170001, 2, "track")
+        );
+    }
+
+    #[test]
+    fn reads_live_rooms_from_every_endpoint() {
+        // The room info endpoint names the id `room_id` and reports the live status…
+        let room: LiveRoom = serde_json::from_value(serde_json::json!({
+            "room_id": 21452505,
+            "title": "t",
+            "live_status": 0,
+        }))
+        .unwrap();
+        assert_eq!((room.room_id, room.live_status), (21452505, 0));
+        assert!(room.uname.is_empty());
+
+        // …while the area and following lists say `roomid` and only list rooms that are live.
+        let room: LiveRoom = serde_json::from_value(serde_json::json!({
+            "roomid": 21452505,
+            "uname": "碧诗",
+            "title": "t",
+            "online": 4096,
+        }))
+        .unwrap();
+        assert_eq!(
+            (room.room_id, room.live_status, room.online),
+            (21452505, 1, 4096)
+        );
+    }
+
+    fn episode(title: &str, long_title: &str) -> Episode {
+        Episode {
+            id: 374717,
+            cid: 1,
+            title: title.to_string(),
+            long_title: long_title.to_string(),
+            badge: String::new(),
+            duration: 0,
+        }
+    }
+
+    #[test]
+    fn numbers_episodes_that_have_a_number() {
+        assert_eq!(episode("3", "再会").display_title(), "EP 3 再会");
+        assert_eq!(episode("3", "").display_title(), "EP 3");
+        assert_eq!(episode("正片", "").display_title(), "正片");
+        assert_eq!(episode("PV", "先导").display_title(), "PV 先导");
+        assert_eq!(
+            episode("3", "").url(),
+            "https://www.bilibili.com/bangumi/play/ep374717"
+        );
+    }
+
+    #[test]
+    fn explains_episode_restrictions() {
+        let explain = |code, message| episode_restriction(code, message).map_err(|e| e.to_string());
+        assert_eq!(explain(0, "0"), Ok(()));
+        assert_eq!(
+            explain(-10403, "抱歉您所在地区不可观看！"),
+            Err("This episode is not available in your region".to_string())
+        );
+        assert_eq!(
+            explain(-10403, "大会员专享限制"),
+            Err("This episode requires a 大会员 membership (大会员专享限制)".to_string())
+        );
+        assert_eq!(
+            explain(-10403, "其他"),
+            Err("This episode is restricted: 其他".to_string())
+        );
+        assert_eq!(
+            explain(-404, "啥都木有"),
+            Err("API error -404: 啥都木有".to_string())
+        );
+    }
+
+    #[test]
+    fn stops_tag_paging_at_the_last_search_page() {
+        assert_eq!(next_search_page(1, 3), Some(2));
+        assert_eq!(next_search_page(3, 3), None);
+        assert_eq!(next_search_page(1, 0), None);
+        assert_eq!(next_search_page(49, 1000), Some(50));
+        assert_eq!(next_search_page(50, 1000), None);
+
+        let data: SearchData = serde_json::from_value(serde_json::json!({"result": null})).unwrap();
+        assert_eq!(data.num_pages, 1);
+        let data: SearchData =
+            serde_json::from_value(serde_json::json!({"result": [], "numPages": 7})).unwrap();
+        assert_eq!(data.num_pages, 7);
+    }
+
+    #[test]
+    fn encodes_search_keywords() {
+        assert_eq!(
+            search_url("C++ & 原神 #1"),
+            "https://api.bilibili.com/x/web-interface/search/type?search_type=video\
+             &keyword=C%2B%2B%20%26%20%E5%8E%9F%E7%A5%9E%20%231"
+        );
+    }
+
+    #[test]
+    fn keeps_keyword_matches_as_ranges() {
+        let title = search_result(serde_json::json!({})).title;
+        assert_eq!(title.text, "【MV】保加利亚妖王");
+        assert_eq!(title.matched_words(), ["保加利亚"]);
+        assert_eq!(&title.text[title.matches[0].clone()], "保加利亚");
+    }
+
+    #[test]
+    fn decodes_html_entities() {
+        let title = Highlighted::parse(
+            r#"Tom &amp; Jerry &quot;<em class="keyword">猫和老鼠</em>&quot; &#39;1&#x27; &lt;3 &unknown; a&b"#,
+        );
+        assert_eq!(title.text, r#"Tom & Jerry "猫和老鼠" '1' <3 &unknown; a&b"#);
+        assert_eq!(title.matched_words(), ["猫和老鼠"]);
+    }
+
+    #[test]
+    fn merges_adjacent_and_entity_split_matches() {
+        let title = Highlighted::parse(
+            r#"<em class="keyword">R&amp;</em><em class="keyword">B</em> mix <em class="keyword">r&amp;b</em>"#,
+        );
+        assert_eq!(title.text, "R&B mix r&b");
+        assert_eq!(title.matches, [0..3, 8..11]);
+    }
+
+    #[test]
+    fn carries_matched_words_over_to_other_text() {
+        let desc = Highlighted::with_words("原神 原神启动 启动", ["原神启动", "原神"]);
+        assert_eq!(desc.matches, [0..6, 7..19]);
+    }
 }
@@ -1,8 +1,20 @@
 use crate::api;
+use crate::cover::{self, Cover, CoverState};
+use crate::danmaku::{self, ChatEvent};
+use crate::effect::Effect;
+use crate::recorder::{self, RecordEvent};
+use crate::task::{AppEvent, Tasks};
+use chrono::NaiveDate;
+use image::RgbImage;
 use ratatui::widgets::ListState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
 use tui_input::Input;
 
-#[derive(PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Focusable {
     Search,
     Results,
@@ -30,15 +42,453 @@ impl Focusable {
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum InputMode {
+    Start,
     Normal,
     Editing,
     Command,
     Detail,
     ListNav,
+    Uploader,
+    Favorites,
+    History,
+    Feed,
+    Live,
+    Recordings,
+    Season,
     Help,
 }
 
+/// Where the videos shown in the results list came from.
+pub enum ResultsSource {
+    Search,
+    Favorites(FavoritesPage),
+    WatchLater,
+    Popular {
+        page: u32,
+        no_more: bool,
+    },
+    Weekly {
+        issue: api::WeeklyIssue,
+        latest: u32,
+    },
+    /// The ranking board for `api::RANK_ZONES[zone]`.
+    Ranking {
+        zone: usize,
+    },
+    Recommended(RecommendedFeed),
+    /// Search results carrying the tag `name`. `starts` holds the search page each tag page
+    /// so far began at, the shown one last; `next` is where the following one begins.
+    Tag {
+        name: String,
+        starts: Vec<u32>,
+        next: Option<u32>,
+    },
+}
+
+/// The homepage recommendation batches loaded so far.
+pub struct RecommendedFeed {
+    pub fresh_idx: u32,
+    /// Feedback details for each recommended video, keyed by bvid.
+    pub targets: HashMap<String, api::DislikeTarget>,
+}
+
+/// Entries of the start screen and the commands they run.
+pub const START_MENU: [(&str, &str); 9] = [
+    ("Recommended", ":rcmd"),
+    ("Popular", ":popular"),
+    ("Weekly must-watch", ":weekly"),
+    ("Ranking", ":rank"),
+    ("Following feed", ":feed"),
+    ("Live", ":live"),
+    ("Watch Later", ":later"),
+    ("History", ":history"),
+    ("Favorites", ":fav"),
+];
+
+/// The favorites folder page currently loaded into the results list.
+pub struct FavoritesPage {
+    pub folder: api::FavFolder,
+    pub page: u32,
+    pub has_more: bool,
+    pub keyword: String,
+}
+
+/// What confirming the folder picker does with the chosen folders.
+#[derive(Clone, Copy)]
+pub enum FolderPickerAction {
+    /// Add the video to the checked folders and remove it from the unchecked ones.
+    Favorite,
+    /// Move the video from the folder `from` into the highlighted folder.
+    Move { from: u64 },
+    /// Copy the video from the folder `from` into the highlighted folder.
+    Copy { from: u64 },
+}
+
+/// A popup for choosing favorites folders for the video `aid`.
+pub struct FolderPicker {
+    pub action: FolderPickerAction,
+    pub aid: u64,
+    pub mid: u64,
+    pub folders: Vec<api::FavFolder>,
+    pub checked: Vec<bool>,
+    pub list_state: ListState,
+}
+
+impl FolderPicker {
+    pub fn toggle(&mut self) {
+        if let FolderPickerAction::Favorite = self.action
+            && let Some(checked) = self
+                .list_state
+                .selected()
+                .and_then(|i| self.checked.get_mut(i))
+        {
+            *checked = !*checked;
+        }
+    }
+
+    /// Folder ids the video should be added to and removed from.
+    pub fn changes(&self) -> (Vec<u64>, Vec<u64>) {
+        let mut add = Vec::new();
+        let mut del = Vec::new();
+        for (folder, &checked) in self.folders.iter().zip(&self.checked) {
+            let was_checked = folder.fav_state == 1;
+            if checked && !was_checked {
+                add.push(folder.id);
+            } else if !checked && was_checked {
+                del.push(folder.id);
+            }
+        }
+        (add, del)
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self.action {
+            FolderPickerAction::Favorite => "Favorite in [space] toggle [Enter] save",
+            FolderPickerAction::Move { .. } => "Move to [Enter] choose",
+            FolderPickerAction::Copy { .. } => "Copy to [Enter] choose",
+        }
+    }
+}
+
+/// The logged-in user's favorites folders.
+pub struct FavoritesView {
+    pub folders: Vec<api::FavFolder>,
+    pub list_state: ListState,
+}
+
+impl FavoritesView {
+    pub fn selected_folder(&self) -> Option<&api::FavFolder> {
+        self.list_state.selected().and_then(|i| self.folders.get(i))
+    }
+}
+
+/// The account's watch history, loaded a page at a time.
+pub struct HistoryView {
+    pub entries: Vec<api::HistoryEntry>,
+    pub list_state: ListState,
+    /// Where the next page starts, or `None` once everything has been loaded.
+    pub next: Option<api::HistoryCursor>,
+    /// When set, only entries watched on this day are listed.
+    pub date: Option<NaiveDate>,
+}
+
+impl HistoryView {
+    pub fn selected(&self) -> Option<&api::HistoryEntry> {
+        self.list_state.selected().and_then(|i| self.entries.get(i))
+    }
+}
+
+/// New videos from followed uploaders, loaded a page at a time.
+pub struct FeedView {
+    pub items: Vec<api::FeedItem>,
+    pub list_state: ListState,
+    pub has_more: bool,
+    pub offset: String,
+    /// Items published after this time are new since the previous visit.
+    pub last_seen: i64,
+}
+
+impl FeedView {
+    pub fn selected(&self) -> Option<&api::FeedItem> {
+        self.list_state.selected().and_then(|i| self.items.get(i))
+    }
+}
+
+/// A tab of the live view: followed rooms or one top-level area.
+#[derive(Clone)]
+pub enum LiveTab {
+    Following,
+    Area(api::LiveArea),
+}
+
+impl LiveTab {
+    pub fn label(&self) -> &str {
+        match self {
+            Self::Following => "Following",
+            Self::Area(area) => &area.name,
+        }
+    }
+}
+
+/// Live rooms, grouped into tabs by area.
+pub struct LiveView {
+    pub tabs: Vec<LiveTab>,
+    pub tab: usize,
+    pub rooms: Vec<api::LiveRoom>,
+    pub list_state: ListState,
+}
+
+impl LiveView {
+    pub fn selected(&self) -> Option<&api::LiveRoom> {
+        self.list_state.selected().and_then(|i| self.rooms.get(i))
+    }
+}
+
+/// How many chat lines to keep for the live chat panel.
+const CHAT_HISTORY: usize = 500;
+
+/// The chat of the live room being watched, fed by a background WebSocket task.
+pub struct LiveChat {
+    pub room_id: u64,
+    pub messages: VecDeque<ChatEvent>,
+    pub popularity: u32,
+    events: mpsc::Receiver<ChatEvent>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl LiveChat {
+    pub fn join(room_id: u64) -> Self {
+        let (events, task) = danmaku::watch(room_id);
+        Self {
+            room_id,
+            messages: VecDeque::new(),
+            popularity: 0,
+            events,
+            task,
+        }
+    }
+
+    /// Waits for the next chat event and moves it, with any others already queued, into the
+    /// message list. Never returns once the connection task has ended.
+    pub async fn receive(&mut self) {
+        let Some(event) = self.events.recv().await else {
+            return std::future::pending().await;
+        };
+        self.push(event);
+        while let Ok(event) = self.events.try_recv() {
+            self.push(event);
+        }
+    }
+
+    fn push(&mut self, event: ChatEvent) {
+        match event {
+            ChatEvent::Popularity(popularity) => self.popularity = popularity,
+            event => {
+                if self.messages.len() == CHAT_HISTORY {
+                    self.messages.pop_front();
+                }
+                self.messages.push_back(event);
+            }
+        }
+    }
+}
+
+impl Drop for LiveChat {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Everything the detail view shows about one video, fetched together.
+#[derive(Clone)]
+pub struct VideoDetails {
+    pub info: api::VideoInfo,
+    pub relation: Option<api::Relation>,
+    pub related: Vec<api::VideoInfo>,
+}
+
+impl VideoDetails {
+    pub async fn fetch(bvid: String) -> Result<Self, String> {
+        let mut info = api::get_video_info(&bvid)
+            .await
+            .map_err(|e| e.to_string())?;
+        // Only the video itself is essential; the rest degrades to empty.
+        let (tags, relation, related) = tokio::join!(
+            api::get_video_tags(&bvid),
+            api::get_relation(info.aid),
+            api::get_related(&bvid),
+        );
+        info.tags = tags.unwrap_or_default();
+        Ok(Self {
+            info,
+            // Logged-out users have no relation to show, so a failure just hides it.
+            relation: relation.ok(),
+            related: related.unwrap_or_default(),
+        })
+    }
+}
+
+/// How many videos' details to keep around.
+const DETAIL_CACHE_ENTRIES: usize = 200;
+
+/// Video details by bvid, and which are being fetched to add more.
+pub struct DetailCache {
+    entries: HashMap<String, VideoDetails>,
+    /// Cached bvids, oldest first, for eviction.
+    order: VecDeque<String>,
+    pending: HashSet<String>,
+}
+
+impl DetailCache {
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            pending: HashSet::new(),
+        }
+    }
+
+    pub fn get(&self, bvid: &str) -> Option<&VideoDetails> {
+        self.entries.get(bvid)
+    }
+
+    pub fn insert(&mut self, details: VideoDetails) {
+        let bvid = details.info.bvid.clone();
+        if self.entries.insert(bvid.clone(), details).is_none() {
+            self.order.push_back(bvid);
+        }
+        if self.order.len() > DETAIL_CACHE_ENTRIES
+            && let Some(oldest) = self.order.pop_front()
+        {
+            self.entries.remove(&oldest);
+        }
+    }
+
+    /// Marks `bvid` as being fetched, returning false if it is cached or already on its way.
+    fn start_fetch(&mut self, bvid: &str) -> bool {
+        !self.entries.contains_key(bvid) && self.pending.insert(bvid.to_string())
+    }
+
+    fn finish_fetch(&mut self, bvid: &str) {
+        self.pending.remove(bvid);
+    }
+}
+
+/// A bangumi or PGC season and its episode list.
+pub struct SeasonView {
+    pub season: api::Season,
+    pub list_state: ListState,
+}
+
+impl SeasonView {
+    pub fn selected(&self) -> Option<&api::Episode> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.season.episodes.get(i))
+    }
+}
+
+/// A live room being recorded to disk by a background task.
+pub struct Recording {
+    pub room_id: u64,
+    /// The streamer's name, or the room title when the room info doesn't carry one.
+    pub name: String,
+    pub started: Instant,
+    /// When the recording was stopped; a stopped recording stays listed until dismissed.
+    pub stopped: Option<Instant>,
+    pub bytes: u64,
+    /// How many files the recording has been split into so far.
+    pub parts: u32,
+    pub file: Option<PathBuf>,
+    /// The latest problem the task is waiting out, cleared once data flows again.
+    pub status: Option<String>,
+    events: mpsc::Receiver<RecordEvent>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl Recording {
+    pub fn start(room_id: u64, name: String, dir: PathBuf) -> Self {
+        let (events, task) = recorder::record(room_id, dir);
+        Self {
+            room_id,
+            name,
+            started: Instant::now(),
+            stopped: None,
+            bytes: 0,
+            parts: 0,
+            file: None,
+            status: None,
+            events,
+            task,
+        }
+    }
+
+    /// Waits for the recording task to report progress and applies it. Never returns once the
+    /// task has ended.
+    pub async fn receive(&mut self) {
+        let Some(event) = self.events.recv().await else {
+            return std::future::pending().await;
+        };
+        match event {
+            RecordEvent::FileStarted(path) => {
+                self.parts += 1;
+                self.file = Some(path);
+                self.status = None;
+            }
+            RecordEvent::Written(bytes) => self.bytes += bytes,
+            RecordEvent::Waiting(reason) => self.status = Some(reason),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.task.abort();
+        self.stopped.get_or_insert_with(Instant::now);
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.stopped.unwrap_or_else(Instant::now) - self.started
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// A popup for choosing the stream quality of a live room before playing it.
+pub struct QualityPicker {
+    pub room_id: u64,
+    pub qualities: Vec<api::LiveQuality>,
+    pub list_state: ListState,
+}
+
+/// An uploader's profile and the currently loaded page of their videos.
+pub struct UploaderView {
+    pub mid: u64,
+    pub profile: api::UserProfile,
+    pub videos: Vec<api::SpaceVideo>,
+    pub list_state: ListState,
+    pub page: u32,
+    pub total: u32,
+    pub order: api::SpaceOrder,
+}
+
+impl UploaderView {
+    pub fn page_count(&self) -> u32 {
+        self.total.div_ceil(api::SPACE_PAGE_SIZE).max(1)
+    }
+
+    pub fn selected_bvid(&self) -> Option<String> {
+        self.list_state
+            .selected()
+            .and_then(|i| self.videos.get(i))
+            .map(|v| v.bvid.clone())
+    }
+}
+
 pub struct App {
     pub search_input: Input,
     pub command_input: Input,
@@ -46,8 +496,39 @@ pub struct App {
     pub focused_panel: Focusable,
     pub search_results: Vec<api::VideoResult>,
     pub results_list_state: ListState,
+    pub results_source: ResultsSource,
+    pub start_list_state: ListState,
     pub video_info: Option<api::VideoInfo>,
+    /// The logged-in user's like/coin/favorite state for `video_info`.
+    pub relation: Option<api::Relation>,
+    /// The cover of `video_info`.
+    pub cover: CoverState,
+    pub details: DetailCache,
+    pub tasks: Tasks,
+    /// Side effects asked for since the main loop last ran them, oldest first.
+    pub effects: Vec<Effect>,
+    /// The bvid the detail view is waiting on while its details load.
+    pub detail_loading: Option<String>,
+    pub related: Vec<api::VideoInfo>,
+    pub related_list_state: ListState,
+    /// The tag chip selected in the detail view, as an index into `video_info.tags`.
+    pub selected_tag: Option<usize>,
+    pub uploader: Option<UploaderView>,
+    pub favorites: Option<FavoritesView>,
+    pub folder_picker: Option<FolderPicker>,
+    pub history: Option<HistoryView>,
+    pub feed: Option<FeedView>,
+    pub live: Option<LiveView>,
+    pub quality_picker: Option<QualityPicker>,
+    pub live_chat: Option<LiveChat>,
+    pub recordings: Vec<Recording>,
+    pub season: Option<SeasonView>,
+    pub recordings_list_state: ListState,
+    /// The mode to return to when the detail view is closed, if not the default.
+    pub detail_parent: Option<InputMode>,
     pub last_error: Option<String>,
+    /// Set once the user asks to exit; the main loop stops after the current action.
+    pub should_quit: bool,
 }
 
 impl App {
@@ -55,12 +536,36 @@ impl App {
         Self {
             search_input: Input::default(),
             command_input: Input::default(),
-            mode: InputMode::Normal,
-            focused_panel: Focusable::Search,
+            mode: InputMode::Start,
+            focused_panel: Focusable::Results,
             search_results: Vec::new(),
             results_list_state: ListState::default(),
+            results_source: ResultsSource::Search,
+            start_list_state: ListState::default().with_selected(Some(0)),
             video_info: None,
+            relation: None,
+            cover: CoverState::new(),
+            details: DetailCache::new(),
+            tasks: Tasks::new(),
+            effects: Vec::new(),
+            detail_loading: None,
+            related: Vec::new(),
+            related_list_state: ListState::default(),
+            selected_tag: None,
+            uploader: None,
+            favorites: None,
+            folder_picker: None,
+            history: None,
+            feed: None,
+            live: None,
+            quality_picker: None,
+            live_chat: None,
+            recordings: Vec::new(),
+            season: None,
+            recordings_list_state: ListState::default(),
+            detail_parent: None,
             last_error: None,
+            should_quit: false,
         }
     }
 
@@ -72,21 +577,379 @@ impl App {
         matches!(self.mode, InputMode::Command)
     }
 
-    pub fn play_video(&self) {
-        let bvid = if let Some(info) = &self.video_info {
+    /// The bvid of the video currently shown in the detail view or selected in the active list.
+    pub fn current_bvid(&self) -> Option<String> {
+        if self.mode == InputMode::Uploader {
+            self.uploader.as_ref().and_then(|u| u.selected_bvid())
+        } else if self.mode == InputMode::History {
+            self.history
+                .as_ref()
+                .and_then(|h| h.selected())
+                .map(|entry| entry.history.bvid.clone())
+        } else if self.mode == InputMode::Feed {
+            self.feed
+                .as_ref()
+                .and_then(|f| f.selected())
+                .map(|item| item.bvid.clone())
+        } else if let Some(info) = &self.video_info {
             Some(info.bvid.clone())
-        } else if let Some(selected) = self.results_list_state.selected() {
-            self.search_results.get(selected).map(|v| v.bvid.clone())
+        } else if let Some(bvid) = &self.detail_loading {
+            Some(bvid.clone())
         } else {
+            self.selected_result().map(|v| v.bvid.clone())
+        }
+    }
+
+    pub fn selected_related_bvid(&self) -> Option<String> {
+        self.related_list_state
+            .selected()
+            .and_then(|i| self.related.get(i))
+            .map(|v| v.bvid.clone())
+    }
+
+    /// Moves the tag chip selection one step, starting from the first or last chip.
+    pub fn select_tag(&mut self, forward: bool) {
+        let count = self.video_info.as_ref().map_or(0, |info| info.tags.len());
+        self.selected_tag = match (self.selected_tag, forward) {
+            _ if count == 0 => None,
+            (None, true) => Some(0),
+            (None, false) => Some(count - 1),
+            (Some(i), true) => Some((i + 1) % count),
+            (Some(i), false) => Some((i + count - 1) % count),
+        };
+    }
+
+    /// The name of the tag chip selected in the detail view.
+    pub fn selected_tag_name(&self) -> Option<String> {
+        let info = self.video_info.as_ref()?;
+        info.tags
+            .get(self.selected_tag?)
+            .map(|tag| tag.tag_name.clone())
+    }
+
+    pub fn set_related(&mut self, related: Vec<api::VideoInfo>) {
+        self.related = related;
+        self.related_list_state.select(if self.related.is_empty() {
             None
+        } else {
+            Some(0)
+        });
+    }
+
+    /// Closes the detail view, returning to the view it was opened from.
+    pub fn close_detail(&mut self) {
+        match self.detail_parent {
+            Some(parent) => self.mode = parent,
+            None => {
+                self.mode = InputMode::Normal;
+                self.focused_panel = Focusable::None;
+            }
+        }
+        self.clear_detail();
+    }
+
+    /// Forgets the video shown in the detail view without changing modes.
+    pub fn clear_detail(&mut self) {
+        // Keep likes and coins given meanwhile for the next time the video is opened.
+        if let Some(info) = self.video_info.take() {
+            self.details.insert(VideoDetails {
+                info,
+                relation: self.relation.take(),
+                related: std::mem::take(&mut self.related),
+            });
+        }
+        self.relation = None;
+        self.cover.current = None;
+        self.related.clear();
+        self.related_list_state.select(None);
+        self.selected_tag = None;
+        self.detail_parent = None;
+        self.detail_loading = None;
+    }
+
+    /// Fills the detail view with `details`.
+    pub fn show_details(&mut self, details: VideoDetails) {
+        self.show_cover(&details.info.pic);
+        self.video_info = Some(details.info);
+        self.relation = details.relation;
+        self.set_related(details.related);
+        self.detail_loading = None;
+    }
+
+    /// Shows the cover at `pic` straight away if it was decoded before, and otherwise loads it
+    /// in the background without holding up the rest of the detail view.
+    fn show_cover(&mut self, pic: &str) {
+        if let Some(image) = cover::cached(pic) {
+            self.cover.current = Some(Cover {
+                url: pic.to_string(),
+                image,
+            });
+            return;
+        }
+        let url = pic.to_string();
+        self.tasks.spawn_quiet(async move {
+            let result = cover::load(&url).await;
+            Ok(AppEvent::Cover { url, result })
+        });
+    }
+
+    /// Shows a cover loaded in the background if its video is still the one on screen.
+    pub fn cover_loaded(&mut self, url: String, image: Arc<RgbImage>) {
+        if self.video_info.as_ref().is_some_and(|info| info.pic == url) {
+            self.cover.current = Some(Cover { url, image });
+        }
+    }
+
+    /// Starts fetching the details of `bvid` in the background unless they are cached or
+    /// already on their way.
+    pub fn fetch_details(&mut self, bvid: &str) {
+        if !self.details.start_fetch(bvid) {
+            return;
+        }
+        let bvid = bvid.to_string();
+        self.tasks.spawn_quiet(async move {
+            let result = VideoDetails::fetch(bvid.clone()).await.map(Box::new);
+            Ok(AppEvent::Details { bvid, result })
+        });
+    }
+
+    /// Caches details fetched in the background, showing them if the detail view waits on them.
+    pub fn details_fetched(&mut self, bvid: &str, result: Result<Box<VideoDetails>, String>) {
+        self.details.finish_fetch(bvid);
+        let waiting = self.detail_loading.as_deref() == Some(bvid);
+        match result {
+            Ok(details) => {
+                if waiting {
+                    self.show_details((*details).clone());
+                }
+                self.details.insert(*details);
+            }
+            Err(e) if waiting => {
+                self.detail_loading = None;
+                self.last_error = Some(e);
+            }
+            Err(_) => {}
+        }
+    }
+
+    /// Replaces the results list with `results` from `source`, selecting the first entry.
+    pub fn set_results(&mut self, results: Vec<api::VideoResult>, source: ResultsSource) {
+        self.search_results = results;
+        self.results_source = source;
+        self.results_list_state
+            .select(if self.search_results.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+    }
+
+    pub fn results_title(&self) -> String {
+        match &self.results_source {
+            ResultsSource::Search => "Results".to_string(),
+            ResultsSource::Favorites(fav) => {
+                let mut title = format!("Favorites: {} (page {})", fav.folder.title, fav.page);
+                if !fav.keyword.is_empty() {
+                    title.push_str(&format!(" matching \"{}\"", fav.keyword));
+                }
+                title
+            }
+            ResultsSource::WatchLater => format!(
+                "Watch Later ({}) [d] remove [C] clear watched [P] play all",
+                self.search_results.len()
+            ),
+            ResultsSource::Popular { page, .. } => format!("Popular (page {}) [h/l] page", page),
+            ResultsSource::Weekly { issue, .. } => format!(
+                "Weekly must-watch #{}: {} [h/l] older/newer",
+                issue.number, issue.subject
+            ),
+            ResultsSource::Ranking { zone } => {
+                format!("Ranking: {} [h/l] zone", api::RANK_ZONES[*zone].label)
+            }
+            ResultsSource::Recommended(_) => {
+                "Recommended [l] load more [x/X] not interested in video/uploader".to_string()
+            }
+            ResultsSource::Tag { name, starts, .. } => {
+                format!("Tag #{} (page {}) [h/l] page", name, starts.len())
+            }
+        }
+    }
+
+    /// Shows the outcome of an action in the status line, clearing any previous error on success.
+    pub fn report(&mut self, result: Result<(), String>) {
+        match result {
+            Ok(_) => self.last_error = None,
+            Err(e) => self.last_error = Some(e),
+        }
+    }
+
+    pub fn selected_result(&self) -> Option<&api::VideoResult> {
+        self.results_list_state
+            .selected()
+            .and_then(|i| self.search_results.get(i))
+    }
+
+    /// Plays every video in the results list in order as a single mpv playlist.
+    pub fn play_all(&mut self) {
+        if self.search_results.is_empty() {
+            return;
+        }
+        let args = self
+            .search_results
+            .iter()
+            .map(|v| format!("https://www.bilibili.com/video/{}", v.bvid))
+            .collect();
+        self.effects.push(Effect::Play {
+            args,
+            what: "videos",
+        });
+    }
+
+    /// Plays the current video, resuming from the saved position when in the history view.
+    pub fn play_video(&mut self) {
+        let start = match self.mode {
+            InputMode::History => self
+                .history
+                .as_ref()
+                .and_then(|h| h.selected())
+                .and_then(|entry| entry.resume_at()),
+            _ => None,
         };
+        if let Some(bvid) = self.current_bvid() {
+            self.effects.push(Effect::play_video(&bvid, start));
+        }
+    }
+}
 
-        if let Some(bvid) = bvid {
-            let url = format!("https://www.bilibili.com/video/{}", bvid);
-            std::process::Command::new("mpv")
-                .arg(url)
-                .spawn()
-                .expect("failed to play video");
+/// Moves a list selection one row down, wrapping around to the top.
+pub fn select_next(state: &mut ListState, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let i = match state.selected() {
+        Some(i) if i + 1 < len => i + 1,
+        _ => 0,
+    };
+    state.select(Some(i));
+}
+
+/// Moves a list selection one row up, wrapping around to the bottom.
+pub fn select_prev(state: &mut ListState, len: usize) {
+    if len == 0 {
+        return;
+    }
+    let i = match state.selected() {
+        Some(0) => len - 1,
+        Some(i) => i - 1,
+        None => 0,
+    };
+    state.select(Some(i));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{details, folder, picker};
+
+    #[test]
+    fn reports_only_the_folders_whose_state_changed() {
+        let mut picker = picker(
+            FolderPickerAction::Favorite,
+            vec![folder(1, 1), folder(2, 0), folder(3, 1), folder(4, 0)],
+        );
+        assert_eq!(picker.changes(), (vec![], vec![]));
+
+        for i in [0, 1] {
+            picker.list_state.select(Some(i));
+            picker.toggle();
+        }
+        assert_eq!(picker.changes(), (vec![2], vec![1]));
+
+        // Toggling back undoes the change.
+        picker.toggle();
+        assert_eq!(picker.changes(), (vec![], vec![1]));
+    }
+
+    #[test]
+    fn leaves_checks_alone_when_moving_or_copying() {
+        let mut picker = picker(
+            FolderPickerAction::Move { from: 1 },
+            vec![folder(1, 1), folder(2, 0)],
+        );
+        picker.list_state.select(Some(1));
+        picker.toggle();
+        assert_eq!(picker.checked, [true, false]);
+        assert_eq!(picker.changes(), (vec![], vec![]));
+    }
+
+    #[test]
+    fn evicts_the_oldest_details_first() {
+        let mut cache = DetailCache::new();
+        for i in 0..DETAIL_CACHE_ENTRIES {
+            cache.insert(details(&format!("BV{}", i)));
         }
+        // Replacing an entry doesn't make it count twice.
+        cache.insert(details("BV0"));
+        assert!(cache.get("BV0").is_some());
+
+        cache.insert(details("BVnew"));
+        assert!(cache.get("BV0").is_none());
+        assert!(cache.get("BV1").is_some());
+        assert!(cache.get("BVnew").is_some());
+        assert_eq!(cache.entries.len(), DETAIL_CACHE_ENTRIES);
+    }
+
+    #[test]
+    fn fetches_each_video_once() {
+        let mut cache = DetailCache::new();
+        assert!(cache.start_fetch("BV1"));
+        assert!(!cache.start_fetch("BV1"));
+        cache.finish_fetch("BV1");
+        cache.insert(details("BV1"));
+        assert!(!cache.start_fetch("BV1"));
+        // A failed fetch may be retried.
+        assert!(cache.start_fetch("BV2"));
+        cache.finish_fetch("BV2");
+        assert!(cache.start_fetch("BV2"));
+    }
+
+    #[test]
+    fn shows_fetched_details_only_when_waiting_for_them() {
+        let mut app = App::new();
+        app.detail_loading = Some("BV1".to_string());
+
+        app.details_fetched("BV2", Err("prefetch failed".to_string()));
+        assert_eq!(app.last_error, None);
+        app.details_fetched("BV2", Ok(Box::new(details("BV2"))));
+        assert!(app.video_info.is_none());
+        assert!(app.details.get("BV2").is_some());
+
+        app.details_fetched("BV1", Ok(Box::new(details("BV1"))));
+        assert_eq!(
+            app.video_info.as_ref().map(|info| info.bvid.as_str()),
+            Some("BV1")
+        );
+        assert_eq!(app.detail_loading, None);
+        assert!(app.details.get("BV1").is_some());
+    }
+
+    #[test]
+    fn shows_a_late_cover_only_for_the_video_on_screen() {
+        let mut app = App::new();
+        let mut shown = details("BV1");
+        shown.info.pic = "https://i0.hdslb.com/bfs/archive/shown.jpg".to_string();
+        app.video_info = Some(shown.info);
+
+        let image = Arc::new(RgbImage::new(1, 1));
+        app.cover_loaded(
+            "https://i0.hdslb.com/bfs/archive/old.jpg".to_string(),
+            image.clone(),
+        );
+        assert!(app.cover.current.is_none());
+        app.cover_loaded(
+            "https://i0.hdslb.com/bfs/archive/shown.jpg".to_string(),
+            image,
+        );
+        assert!(app.cover.current.is_some());
     }
 }
@@ -0,0 +1,306 @@
+use crate::storage;
+use std::collections::BTreeSet;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// Whether requests are answered from the cache alone, without touching the network.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+/// Cache keys whose stale entries are being refreshed in the background right now.
+static REFRESHING: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+static HITS: AtomicU64 = AtomicU64::new(0);
+static STALE_HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+
+/// How long a cached response may stand in for the network.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Never stored, because the response holds short-lived stream URLs or tokens.
+    NoStore,
+    /// Served as is while younger than `fresh`, and for `stale` longer while a background request
+    /// refreshes it. Older entries are only served offline.
+    Keep { fresh: Duration, stale: Duration },
+}
+
+const fn keep(fresh: Duration, stale: Duration) -> Policy {
+    Policy::Keep { fresh, stale }
+}
+
+const fn minutes(n: u64) -> Duration {
+    Duration::from_secs(n * 60)
+}
+
+const fn hours(n: u64) -> Duration {
+    minutes(n * 60)
+}
+
+const fn days(n: u64) -> Duration {
+    hours(n * 24)
+}
+
+/// Always asks the network, keeping the response only for offline use. The default for the
+/// logged-in user's own lists, which change under the user's own actions.
+const NETWORK_FIRST: Policy = keep(Duration::ZERO, Duration::ZERO);
+
+/// Policies by URL path prefix; the first match wins and unlisted endpoints are network-first.
+const POLICIES: [(&str, Policy); 19] = [
+    ("/pgc/player/web/playurl", Policy::NoStore),
+    ("/room/v1/Room/playUrl", Policy::NoStore),
+    ("/xlive/web-room/v1/index/getDanmuInfo", Policy::NoStore),
+    ("/x/web-interface/search/type", keep(minutes(10), hours(1))),
+    ("/x/web-interface/view", keep(minutes(5), days(1))),
+    ("/x/web-interface/archive/related", keep(hours(1), days(1))),
+    ("/x/web-interface/archive/relation", NETWORK_FIRST),
+    ("/x/tag/archive/tags", keep(days(1), days(7))),
+    ("/x/web-interface/card", keep(minutes(10), days(1))),
+    ("/x/space/wbi/arc/search", keep(minutes(10), days(1))),
+    ("/x/web-interface/popular/series", keep(hours(1), days(7))),
+    ("/x/web-interface/popular", keep(minutes(5), hours(1))),
+    ("/x/web-interface/ranking/v2", keep(minutes(30), days(1))),
+    ("/pgc/view/web/season", keep(hours(1), days(1))),
+    ("/room/v1/Area/getList", keep(days(1), days(7))),
+    ("/room/v1/Room/get_info", keep(minutes(1), minutes(10))),
+    (
+        "/xlive/web-interface/v1/second/getList",
+        keep(minutes(1), minutes(10)),
+    ),
+    (
+        "/xlive/web-ucenter/v1/xfetter/GetWebList",
+        keep(minutes(1), minutes(10)),
+    ),
+    // The login state must follow a login or a changed cookie at once.
+    ("/x/web-interface/nav", NETWORK_FIRST),
+];
+
+/// The caching policy for a request to `url`.
+pub fn policy(url: &str) -> Policy {
+    let Ok(url) = url::Url::parse(url) else {
+        return Policy::NoStore;
+    };
+    POLICIES
+        .iter()
+        .find(|(prefix, _)| url.path().starts_with(prefix))
+        .map_or(NETWORK_FIRST, |(_, policy)| *policy)
+}
+
+#[derive(Debug, PartialEq)]
+enum Freshness {
+    Fresh,
+    Stale,
+    Expired,
+}
+
+fn freshness(policy: Policy, age: Duration) -> Freshness {
+    match policy {
+        Policy::Keep { fresh, .. } if age < fresh => Freshness::Fresh,
+        Policy::Keep { fresh, stale } if age < fresh + stale => Freshness::Stale,
+        _ => Freshness::Expired,
+    }
+}
+
+/// What the cache has for a request.
+pub enum Lookup {
+    /// A body to use without asking the network.
+    Fresh(String),
+    /// A body to use now while a fresh one is fetched in the background.
+    Stale(String),
+    Miss,
+}
+
+/// `url` without the WBI signature, which changes with every request for the same resource.
+fn normalize(url: &str) -> String {
+    let Ok(mut parsed) = url::Url::parse(url) else {
+        return url.to_string();
+    };
+    let params: Vec<(String, String)> = parsed
+        .query_pairs()
+        .filter(|(name, _)| name != "wts" && name != "w_rid")
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    if params.is_empty() {
+        parsed.set_query(None);
+    } else {
+        parsed.query_pairs_mut().clear().extend_pairs(params);
+    }
+    parsed.into()
+}
+
+/// The cache key for `url` as seen by the account logged in with `cookie`, so switching
+/// accounts never shows another account's lists.
+pub fn key(url: &str, cookie: &str) -> String {
+    format!(
+        "{:x}",
+        md5::compute(format!("{}\n{}", cookie, normalize(url)))
+    )
+}
+
+fn responses_dir() -> io::Result<PathBuf> {
+    let dir = storage::cache_dir()?.join("responses");
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn entry_path(key: &str) -> io::Result<PathBuf> {
+    Ok(responses_dir()?.join(format!("{}.json", key)))
+}
+
+/// Looks `url` up. Offline, any stored entry counts as fresh, however old.
+pub fn lookup(url: &str, cookie: &str) -> Lookup {
+    let policy = policy(url);
+    if policy == Policy::NoStore {
+        return Lookup::Miss;
+    }
+    let entry = entry_path(&key(url, cookie)).ok().and_then(|path| {
+        let modified = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .ok()?;
+        Some((std::fs::read_to_string(&path).ok()?, modified))
+    });
+    let Some((body, stored)) = entry else {
+        MISSES.fetch_add(1, Ordering::Relaxed);
+        return Lookup::Miss;
+    };
+    let age = SystemTime::now().duration_since(stored).unwrap_or_default();
+    match freshness(policy, age) {
+        _ if is_offline() => {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            Lookup::Fresh(body)
+        }
+        Freshness::Fresh => {
+            HITS.fetch_add(1, Ordering::Relaxed);
+            Lookup::Fresh(body)
+        }
+        Freshness::Stale => {
+            STALE_HITS.fetch_add(1, Ordering::Relaxed);
+            Lookup::Stale(body)
+        }
+        Freshness::Expired => {
+            MISSES.fetch_add(1, Ordering::Relaxed);
+            Lookup::Miss
+        }
+    }
+}
+
+/// Stores a successful response for `url`, unless its policy forbids it.
+pub fn store(url: &str, cookie: &str, body: &str) {
+    if policy(url) == Policy::NoStore {
+        return;
+    }
+    if let Ok(path) = entry_path(&key(url, cookie)) {
+        // The cache is only an optimisation, so failing to write it is fine.
+        let _ = std::fs::write(path, body);
+    }
+}
+
+/// Claims the background refresh of `key`, returning false if one is already under way.
+pub fn start_refresh(key: &str) -> bool {
+    REFRESHING.lock().unwrap().insert(key.to_string())
+}
+
+pub fn finish_refresh(key: &str) {
+    REFRESHING.lock().unwrap().remove(key);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+/// What the cache holds on disk and how well it has served this session.
+pub struct Stats {
+    pub entries: usize,
+    pub bytes: u64,
+    pub hits: u64,
+    pub stale_hits: u64,
+    pub misses: u64,
+}
+
+pub fn stats() -> io::Result<Stats> {
+    let mut stats = Stats {
+        entries: 0,
+        bytes: 0,
+        hits: HITS.load(Ordering::Relaxed),
+        stale_hits: STALE_HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+    };
+    for entry in std::fs::read_dir(responses_dir()?)? {
+        stats.entries += 1;
+        stats.bytes += entry?.metadata()?.len();
+    }
+    Ok(stats)
+}
+
+/// Deletes every stored response, returning how many there were.
+pub fn clear() -> io::Result<usize> {
+    let mut removed = 0;
+    for entry in std::fs::read_dir(responses_dir()?)? {
+        std::fs::remove_file(entry?.path())?;
+        removed += 1;
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_first_matching_policy() {
+        assert_eq!(
+            policy("https://api.bilibili.com/x/web-interface/popular/series/one?number=5"),
+            keep(hours(1), days(7))
+        );
+        assert_eq!(
+            policy("https://api.bilibili.com/x/web-interface/popular?pn=1&ps=20"),
+            keep(minutes(5), hours(1))
+        );
+        assert_eq!(
+            policy(
+                "https://api.live.bilibili.com/room/v1/Room/playUrl?cid=1&qn=10000&platform=web"
+            ),
+            Policy::NoStore
+        );
+        assert_eq!(
+            policy("https://api.bilibili.com/x/v2/history/toview"),
+            NETWORK_FIRST
+        );
+        assert_eq!(
+            policy("https://api.bilibili.com/x/web-interface/nav"),
+            NETWORK_FIRST
+        );
+    }
+
+    #[test]
+    fn ignores_the_wbi_signature_in_keys() {
+        let signed = |wts: u64, w_rid: &str| {
+            format!(
+                "https://api.bilibili.com/x/space/wbi/arc/search?mid=2&pn=1&wts={}&w_rid={}",
+                wts, w_rid
+            )
+        };
+        assert_eq!(key(&signed(1, "aa"), ""), key(&signed(2, "bb"), ""));
+        assert_ne!(
+            key(&signed(1, "aa"), ""),
+            key(&signed(1, "aa"), "SESSDATA=x")
+        );
+        assert_eq!(
+            normalize(&signed(1, "aa")),
+            "https://api.bilibili.com/x/space/wbi/arc/search?mid=2&pn=1"
+        );
+    }
+
+    #[test]
+    fn ages_entries_from_fresh_to_stale_to_expired() {
+        let policy = keep(minutes(5), hours(1));
+        assert_eq!(freshness(policy, minutes(4)), Freshness::Fresh);
+        assert_eq!(freshness(policy, minutes(30)), Freshness::Stale);
+        assert_eq!(freshness(policy, hours(2)), Freshness::Expired);
+        assert_eq!(freshness(NETWORK_FIRST, Duration::ZERO), Freshness::Expired);
+    }
+}
@@ -1,11 +1,41 @@
-use crate::app::{App, InputMode};
 use crate::api;
+use crate::app::{
+    App, FavoritesPage, FavoritesView, FeedView, Focusable, FolderPicker, FolderPickerAction,
+    HistoryView, InputMode, LiveTab, LiveView, QualityPicker, RecommendedFeed, ResultsSource,
+    SeasonView, UploaderView,
+};
+use crate::effect::Effect;
+use crate::storage;
+use crate::task::{AppEvent, Slot};
+use chrono::{Local, NaiveDate};
 use url::Url;
 
 #[derive(Debug, PartialEq)]
 pub enum Command {
     PlayUrl(String),
     ShowVideoInfo(String),
+    ShowUploader(String),
+    ShowFavorites,
+    CreateFavFolder(String),
+    RenameFavFolder(String),
+    ShowHistory(Option<NaiveDate>),
+    ShowWatchLater,
+    ShowFeed,
+    ShowStart,
+    ShowPopular,
+    ShowWeekly(Option<u32>),
+    ShowRanking(usize),
+    ShowRecommended,
+    ShowLive,
+    PlayLiveRoom(u64),
+    RecordLiveRoom(u64),
+    ShowRecordings,
+    ShowSeason(api::SeasonId),
+    ShowTag(String),
+    CacheStats,
+    ClearCache,
+    /// Turns offline mode on or off, or toggles it without an argument.
+    SetOffline(Option<bool>),
     Help,
     Quit,
 }
@@ -17,7 +47,7 @@ pub fn parse(input: &str) -> Result<Command, String> {
     }
 
     let parts: Vec<&str> = input[1..].split_whitespace().collect();
-    let command = parts.get(0).ok_or("No command entered")?;
+    let command = parts.first().ok_or("No command entered")?;
     let args = &parts[1..];
 
     match *command {
@@ -33,6 +63,94 @@ pub fn parse(input: &str) -> Result<Command, String> {
             }
             Ok(Command::ShowVideoInfo(args[0].to_string()))
         }
+        "up" => {
+            if args.is_empty() {
+                return Err("Usage: :up <mid|name>".to_string());
+            }
+            Ok(Command::ShowUploader(args.join(" ")))
+        }
+        "fav" => Ok(Command::ShowFavorites),
+        "fav-new" => {
+            if args.is_empty() {
+                return Err("Usage: :fav-new <title>".to_string());
+            }
+            Ok(Command::CreateFavFolder(args.join(" ")))
+        }
+        "fav-rename" => {
+            if args.is_empty() {
+                return Err("Usage: :fav-rename <title>".to_string());
+            }
+            Ok(Command::RenameFavFolder(args.join(" ")))
+        }
+        "history" => match args {
+            [] => Ok(Command::ShowHistory(None)),
+            [date] => NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map(|date| Command::ShowHistory(Some(date)))
+                .map_err(|_| "Usage: :history [YYYY-MM-DD]".to_string()),
+            _ => Err("Usage: :history [YYYY-MM-DD]".to_string()),
+        },
+        "later" => Ok(Command::ShowWatchLater),
+        "feed" => Ok(Command::ShowFeed),
+        "start" => Ok(Command::ShowStart),
+        "popular" => Ok(Command::ShowPopular),
+        "rcmd" => Ok(Command::ShowRecommended),
+        "live" => Ok(Command::ShowLive),
+        "room" => match args {
+            [room_id] => room_id
+                .parse()
+                .map(Command::PlayLiveRoom)
+                .map_err(|_| "Usage: :room <room_id>".to_string()),
+            _ => Err("Usage: :room <room_id>".to_string()),
+        },
+        "record" => match args {
+            [room_id] => room_id
+                .parse()
+                .map(Command::RecordLiveRoom)
+                .map_err(|_| "Usage: :record <room_id>".to_string()),
+            _ => Err("Usage: :record <room_id>".to_string()),
+        },
+        "recordings" => Ok(Command::ShowRecordings),
+        "season" => match args {
+            [id] => extract_season_id(id)
+                .map(Command::ShowSeason)
+                .ok_or_else(|| "Usage: :season <ss…|ep…|bangumi URL>".to_string()),
+            _ => Err("Usage: :season <ss…|ep…|bangumi URL>".to_string()),
+        },
+        "tag" => {
+            if args.is_empty() {
+                return Err("Usage: :tag <name>".to_string());
+            }
+            Ok(Command::ShowTag(args.join(" ")))
+        }
+        "cache" => match args {
+            ["stats"] => Ok(Command::CacheStats),
+            ["clear"] => Ok(Command::ClearCache),
+            _ => Err("Usage: :cache stats|clear".to_string()),
+        },
+        "offline" => match args {
+            [] => Ok(Command::SetOffline(None)),
+            ["on"] => Ok(Command::SetOffline(Some(true))),
+            ["off"] => Ok(Command::SetOffline(Some(false))),
+            _ => Err("Usage: :offline [on|off]".to_string()),
+        },
+        "weekly" => match args {
+            [] => Ok(Command::ShowWeekly(None)),
+            [number] => number
+                .parse()
+                .map(|number| Command::ShowWeekly(Some(number)))
+                .map_err(|_| "Usage: :weekly [N]".to_string()),
+            _ => Err("Usage: :weekly [N]".to_string()),
+        },
+        "rank" => match args {
+            [] => Ok(Command::ShowRanking(0)),
+            [zone] => api::find_rank_zone(zone)
+                .map(Command::ShowRanking)
+                .ok_or_else(|| {
+                    let zones: Vec<&str> = api::RANK_ZONES.iter().map(|z| z.name).collect();
+                    format!("Unknown zone {}; try one of: {}", zone, zones.join(", "))
+                }),
+            _ => Err("Usage: :rank [zone]".to_string()),
+        },
         "help" => Ok(Command::Help),
         "q" => Ok(Command::Quit),
         _ => Err(format!("Unknown command: {}", command)),
@@ -43,52 +161,1431 @@ fn extract_bvid(input: &str) -> Option<String> {
     if input.starts_with("BV") {
         return Some(input.to_string());
     }
-    if let Ok(url) = Url::parse(input) {
-        if let Some(domain) = url.domain() {
-            if domain.ends_with("bilibili.com") {
-                if let Some(path_segments) = url.path_segments() {
-                    for segment in path_segments {
-                        if segment.starts_with("BV") {
-                            return Some(segment.to_string());
-                        }
-                    }
-                }
+    if let Ok(url) = Url::parse(input)
+        && let Some(domain) = url.domain()
+        && domain.ends_with("bilibili.com")
+        && let Some(path_segments) = url.path_segments()
+    {
+        for segment in path_segments {
+            if segment.starts_with("BV") {
+                return Some(segment.to_string());
             }
         }
     }
     None
 }
 
-pub async fn execute(command: Command, app: &mut App) -> Result<(), String> {
+/// Recognizes `ss…`/`ep…` ids, bare or as the last path segment of a `/bangumi/play/` URL.
+fn extract_season_id(input: &str) -> Option<api::SeasonId> {
+    let parse = |id: &str| {
+        if let Some(number) = id.strip_prefix("ss") {
+            number.parse().ok().map(api::SeasonId::Season)
+        } else if let Some(number) = id.strip_prefix("ep") {
+            number.parse().ok().map(api::SeasonId::Episode)
+        } else {
+            None
+        }
+    };
+    if let Some(id) = parse(input) {
+        return Some(id);
+    }
+    let url = Url::parse(input).ok()?;
+    if !url.domain()?.ends_with("bilibili.com") {
+        return None;
+    }
+    let mut segments = url.path_segments()?.filter(|segment| !segment.is_empty());
+    if segments.next()? != "bangumi" || segments.next()? != "play" {
+        return None;
+    }
+    segments.next().and_then(parse)
+}
+
+/// Runs `command`. Anything that needs the network is started in the background, and its
+/// result is brought in by [`apply`] once it arrives.
+pub fn execute(command: Command, app: &mut App) -> Result<(), String> {
     match command {
         Command::PlayUrl(url) => {
-            std::process::Command::new("mpv")
-                .arg(&url)
-                .spawn()
-                .map_err(|e| format!("Failed to play video: {}", e))?;
+            app.effects.push(Effect::Play {
+                args: vec![url.clone()],
+                what: "video",
+            });
             app.last_error = Some(format!("Playing: {}", url));
-            Ok(())
         }
         Command::ShowVideoInfo(url_or_bvid) => {
             if let Some(bvid) = extract_bvid(&url_or_bvid) {
-                match api::get_video_info(&bvid).await {
-                    Ok(info) => {
-                        app.video_info = Some(info);
-                        app.mode = InputMode::Detail;
-                        Ok(())
-                    }
-                    Err(e) => Err(e.to_string()),
-                }
+                open_details(&bvid, app);
+            } else if let Some(id) = extract_season_id(&url_or_bvid) {
+                open_season(id, app);
             } else {
-                Err("Invalid Bilibili URL or BVID".to_string())
+                return Err("Invalid Bilibili URL or BVID".to_string());
             }
         }
+        Command::ShowUploader(mid_or_name) => {
+            app.tasks
+                .spawn(Some(Slot::View), "Loading uploader", async move {
+                    let mid = match mid_or_name.parse::<u64>() {
+                        Ok(mid) => mid,
+                        Err(_) => api::find_user(&mid_or_name)
+                            .await
+                            .map_err(|e| e.to_string())?,
+                    };
+                    load_uploader(mid).await
+                });
+        }
+        Command::ShowFavorites => {
+            app.tasks
+                .spawn(Some(Slot::View), "Loading favorites", async {
+                    let mid = api::current_user_mid().await.map_err(|e| e.to_string())?;
+                    let folders = api::get_fav_folders(mid, None)
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    Ok(AppEvent::FavoritesOpened(folders))
+                });
+        }
+        Command::CreateFavFolder(title) => {
+            if app.favorites.is_some() {
+                app.mode = InputMode::Favorites;
+                app.focused_panel = Focusable::Results;
+            }
+            app.tasks.spawn(None, "Creating folder", async move {
+                let folder = api::create_fav_folder(&title)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                let message = format!("Created folder: {}", folder.title);
+                fetch_fav_folders(Some(message)).await
+            });
+        }
+        Command::RenameFavFolder(title) => {
+            let folder = app
+                .favorites
+                .as_ref()
+                .and_then(|view| view.selected_folder())
+                .ok_or("Select a folder in :fav to rename it")?;
+            let id = folder.id;
+            app.mode = InputMode::Favorites;
+            app.focused_panel = Focusable::Results;
+            app.tasks.spawn(None, "Renaming folder", async move {
+                api::rename_fav_folder(id, &title)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                fetch_fav_folders(Some(format!("Renamed folder to: {}", title))).await
+            });
+        }
+        Command::ShowHistory(date) => {
+            // Start the cursor at the end of the requested day so paging walks back through it.
+            let next = date.map(|date| api::HistoryCursor {
+                max: 0,
+                view_at: local_timestamp(date.succ_opt().unwrap_or(date)),
+            });
+            app.history = Some(HistoryView {
+                entries: Vec::new(),
+                list_state: Default::default(),
+                next,
+                date,
+            });
+            spawn_history_page(true, app);
+        }
+        Command::ShowWatchLater => {
+            app.tasks
+                .spawn(Some(Slot::View), "Loading Watch Later", async {
+                    let videos = api::get_watch_later().await.map_err(|e| e.to_string())?;
+                    Ok(results(videos, ResultsSource::WatchLater))
+                });
+        }
+        Command::ShowFeed => {
+            app.feed = Some(FeedView {
+                items: Vec::new(),
+                list_state: Default::default(),
+                has_more: true,
+                offset: String::new(),
+                last_seen: 0,
+            });
+            spawn_feed_page(true, app);
+        }
+        Command::ShowStart => {
+            app.mode = InputMode::Start;
+            app.focused_panel = Focusable::Results;
+        }
+        Command::ShowPopular => load_popular(1, app),
+        Command::ShowWeekly(number) => {
+            app.tasks
+                .spawn(Some(Slot::View), "Loading weekly", async move {
+                    let latest = api::get_latest_weekly_number()
+                        .await
+                        .map_err(|e| e.to_string())?;
+                    fetch_weekly(number.unwrap_or(latest), latest).await
+                });
+        }
+        Command::ShowRanking(zone) => load_ranking(zone, app),
+        Command::ShowRecommended => {
+            let feed = RecommendedFeed {
+                fresh_idx: 0,
+                targets: Default::default(),
+            };
+            show_results(Vec::new(), ResultsSource::Recommended(feed), app);
+            load_recommendations(app);
+        }
+        Command::ShowLive => {
+            app.tasks
+                .spawn(Some(Slot::View), "Loading live rooms", async {
+                    let areas = api::get_live_areas().await.map_err(|e| e.to_string())?;
+                    let mut tabs = vec![LiveTab::Following];
+                    tabs.extend(areas.into_iter().map(LiveTab::Area));
+                    // Logged-out users have no followed rooms, so start them on the first area.
+                    let tab = if api::current_user_mid().await.is_ok() {
+                        0
+                    } else {
+                        1
+                    };
+                    let rooms = fetch_live_rooms(&tabs[tab]).await?;
+                    Ok(AppEvent::LiveOpened { tabs, tab, rooms })
+                });
+        }
+        Command::PlayLiveRoom(room_id) => open_quality_picker(room_id, app),
+        Command::ShowSeason(id) => open_season(id, app),
+        Command::ShowTag(name) => load_tag(name, vec![1], app),
+        Command::CacheStats => app.effects.push(Effect::ReportCacheStats),
+        Command::ClearCache => app.effects.push(Effect::ClearCache),
+        Command::SetOffline(offline) => app.effects.push(Effect::SetOffline(offline)),
+        Command::RecordLiveRoom(room_id) => start_recording(room_id, app),
+        Command::ShowRecordings => {
+            if app.recordings_list_state.selected().is_none() && !app.recordings.is_empty() {
+                app.recordings_list_state.select(Some(0));
+            }
+            app.mode = InputMode::Recordings;
+            app.focused_panel = Focusable::Results;
+        }
         Command::Help => {
             app.mode = InputMode::Help;
-            Ok(())
         }
-        Command::Quit => {
-            Ok(())
+        Command::Quit => {}
+    }
+    Ok(())
+}
+
+/// Brings the result of a background task into the app.
+pub fn apply(event: AppEvent, app: &mut App) -> Result<(), String> {
+    match event {
+        AppEvent::Results {
+            videos,
+            source,
+            focus,
+        } => {
+            if focus {
+                // Tags are opened from the detail view, which they replace.
+                if matches!(source, ResultsSource::Tag { .. }) {
+                    app.clear_detail();
+                }
+                show_results(videos, source, app);
+            } else if std::mem::discriminant(&app.results_source) == std::mem::discriminant(&source)
+            {
+                // A reload behind the current view; dropped if the list changed meanwhile.
+                app.set_results(videos, source);
+            }
+        }
+        AppEvent::Recommendations { fresh_idx, batch } => {
+            let ResultsSource::Recommended(feed) = &mut app.results_source else {
+                return Ok(());
+            };
+            feed.fresh_idx = fresh_idx;
+            for (video, target) in batch {
+                if feed.targets.insert(video.bvid.clone(), target).is_none() {
+                    app.search_results.push(video);
+                }
+            }
+            if app.results_list_state.selected().is_none() && !app.search_results.is_empty() {
+                app.results_list_state.select(Some(0));
+            }
+        }
+        AppEvent::Disliked(bvid) => {
+            if let Some(index) = app.search_results.iter().position(|v| v.bvid == bvid) {
+                remove_result(index, app);
+            }
+            app.last_error = Some("Got it, you'll see less like this".to_string());
+        }
+        // Only land on the recommended feed if the user hasn't gone anywhere meanwhile.
+        AppEvent::LoginChecked { logged_in } => {
+            if logged_in && app.mode == InputMode::Start {
+                execute(Command::ShowRecommended, app)?;
+            }
+        }
+        AppEvent::Details { bvid, result } => app.details_fetched(&bvid, result),
+        // The detail view does without a cover that fails to load.
+        AppEvent::Cover { url, result } => {
+            if let Ok(image) = result {
+                app.cover_loaded(url, image);
+            }
+        }
+        AppEvent::Uploader(view) => {
+            app.clear_detail();
+            app.uploader = Some(view);
+            app.mode = InputMode::Uploader;
+            app.focused_panel = Focusable::Results;
+        }
+        AppEvent::UploaderPage {
+            mid,
+            page,
+            order,
+            videos,
+        } => {
+            let Some(view) = app.uploader.as_mut().filter(|view| view.mid == mid) else {
+                return Ok(());
+            };
+            view.videos = videos.videos;
+            view.total = videos.total;
+            view.page = page;
+            view.order = order;
+            view.list_state.select(if view.videos.is_empty() {
+                None
+            } else {
+                Some(0)
+            });
+        }
+        AppEvent::FavoritesOpened(folders) => {
+            let mut view = FavoritesView {
+                folders,
+                list_state: Default::default(),
+            };
+            if !view.folders.is_empty() {
+                view.list_state.select(Some(0));
+            }
+            app.favorites = Some(view);
+            app.mode = InputMode::Favorites;
+            app.focused_panel = Focusable::Results;
+        }
+        AppEvent::FavFoldersChanged { folders, message } => {
+            if let Some(view) = app.favorites.as_mut() {
+                view.folders = folders;
+                let selected = view.list_state.selected().unwrap_or(0);
+                view.list_state.select(if view.folders.is_empty() {
+                    None
+                } else {
+                    Some(selected.min(view.folders.len() - 1))
+                });
+            }
+            if message.is_some() {
+                app.last_error = message;
+            }
+        }
+        AppEvent::FolderPicker(picker) => app.folder_picker = Some(picker),
+        AppEvent::FavoritesSaved {
+            aid,
+            favorited,
+            message,
+        } => {
+            if let (Some(info), Some(relation), Some(favorited)) =
+                (app.video_info.as_mut(), app.relation.as_mut(), favorited)
+                && info.aid == aid
+                && relation.favorite != favorited
+            {
+                relation.favorite = favorited;
+                if favorited {
+                    info.stat.favorite += 1;
+                } else {
+                    info.stat.favorite = info.stat.favorite.saturating_sub(1);
+                }
+            }
+            if let ResultsSource::Favorites(fav) = &app.results_source {
+                let (folder, page, keyword) = (fav.folder.clone(), fav.page, fav.keyword.clone());
+                spawn_fav_page(folder, page, keyword, false, app);
+            }
+            if app.favorites.is_some() {
+                app.tasks.spawn_quiet(fetch_fav_folders(None));
+            }
+            app.last_error = Some(message);
+        }
+        AppEvent::HistoryPage { page, open } => {
+            let Some(view) = app.history.as_mut() else {
+                return Ok(());
+            };
+            let mut exhausted = page.entries.is_empty() || page.next.max == 0;
+            let mut entries = page.entries;
+            if let Some(date) = view.date {
+                let start = local_timestamp(date);
+                if entries.iter().any(|entry| entry.view_at < start) {
+                    exhausted = true;
+                }
+                entries.retain(|entry| entry.view_at >= start);
+            }
+
+            view.entries.extend(entries);
+            view.next = if exhausted { None } else { Some(page.next) };
+            if view.list_state.selected().is_none() && !view.entries.is_empty() {
+                view.list_state.select(Some(0));
+            }
+            if open {
+                app.mode = InputMode::History;
+                app.focused_panel = Focusable::Results;
+            }
+        }
+        AppEvent::HistoryDeleted(aid) => {
+            let Some(view) = app.history.as_mut() else {
+                return Ok(());
+            };
+            let Some(index) = view
+                .entries
+                .iter()
+                .position(|entry| entry.history.oid == aid)
+            else {
+                return Ok(());
+            };
+            view.entries.remove(index);
+            view.list_state.select(if view.entries.is_empty() {
+                None
+            } else {
+                Some(index.min(view.entries.len() - 1))
+            });
+        }
+        AppEvent::WatchLaterRemoved(aid) => {
+            if !matches!(app.results_source, ResultsSource::WatchLater) {
+                return Ok(());
+            }
+            if let Some(index) = app.search_results.iter().position(|v| v.aid == aid) {
+                remove_result(index, app);
+            }
+        }
+        AppEvent::FeedPage { page, last_seen } => {
+            let Some(view) = app.feed.as_mut() else {
+                return Ok(());
+            };
+            view.items.extend(page.items);
+            view.has_more = page.has_more;
+            view.offset = page.offset;
+            if view.list_state.selected().is_none() && !view.items.is_empty() {
+                view.list_state.select(Some(0));
+            }
+            if let Some(last_seen) = last_seen {
+                view.last_seen = last_seen;
+                if let Some(newest) = view.items.first() {
+                    app.effects.push(Effect::MarkFeedSeen(newest.pub_ts));
+                }
+                app.mode = InputMode::Feed;
+                app.focused_panel = Focusable::Results;
+            }
+        }
+        AppEvent::LiveOpened { tabs, tab, rooms } => {
+            let mut view = LiveView {
+                tabs,
+                tab,
+                rooms,
+                list_state: Default::default(),
+            };
+            if !view.rooms.is_empty() {
+                view.list_state.select(Some(0));
+            }
+            app.live = Some(view);
+            app.mode = InputMode::Live;
+            app.focused_panel = Focusable::Results;
+        }
+        AppEvent::LiveRooms { tab, rooms } => {
+            let Some(view) = app.live.as_mut() else {
+                return Ok(());
+            };
+            view.tab = tab;
+            view.rooms = rooms;
+            view.list_state
+                .select(if view.rooms.is_empty() { None } else { Some(0) });
+        }
+        AppEvent::QualityPicker(picker) => app.quality_picker = Some(picker),
+        AppEvent::PlayLive { room_id, url } => {
+            app.effects.push(Effect::play_live_stream(&url));
+            if app.live_chat.as_ref().map(|chat| chat.room_id) != Some(room_id) {
+                app.effects.push(Effect::JoinChat(room_id));
+            }
+            app.mode = InputMode::Live;
+            app.focused_panel = Focusable::Results;
+            app.last_error = Some(format!("Playing live room {}", room_id));
+        }
+        AppEvent::RecordRoom(room) => {
+            if let Some(i) = app
+                .recordings
+                .iter()
+                .position(|r| r.room_id == room.room_id && r.stopped.is_none())
+            {
+                app.recordings_list_state.select(Some(i));
+                app.mode = InputMode::Recordings;
+                return Err(format!("Room {} is already being recorded", room.room_id));
+            }
+            let name = if room.uname.is_empty() {
+                room.title.clone()
+            } else {
+                room.uname.clone()
+            };
+            app.effects.push(Effect::Record {
+                room_id: room.room_id,
+                name,
+            });
+            app.mode = InputMode::Recordings;
+            app.focused_panel = Focusable::Results;
+        }
+        AppEvent::Season { season, episode } => {
+            let selected =
+                episode.and_then(|ep_id| season.episodes.iter().position(|ep| ep.id == ep_id));
+            let mut view = SeasonView {
+                season,
+                list_state: Default::default(),
+            };
+            if !view.season.episodes.is_empty() {
+                view.list_state.select(Some(selected.unwrap_or(0)));
+            }
+            app.season = Some(view);
+            app.mode = InputMode::Season;
+            app.focused_panel = Focusable::Results;
+        }
+        AppEvent::PlayEpisode(episode) => {
+            app.effects.push(Effect::Play {
+                args: vec![episode.url()],
+                what: "episode",
+            });
+            app.last_error = Some(format!("Playing: {}", episode.display_title()));
+        }
+        AppEvent::Done(message) => app.last_error = Some(message),
+        AppEvent::InteractionFailed { snapshot, error } => {
+            restore(app, snapshot);
+            return Err(error);
+        }
+        AppEvent::Tripled { snapshot, result } => {
+            // Replace the guess with what the server says actually happened.
+            let (aid, mut stat, mut relation) = snapshot;
+            if result.like {
+                like(&mut stat, &mut relation);
+            }
+            relation.coin += result.multiply;
+            stat.coin += u64::from(result.multiply);
+            if result.fav && !relation.favorite {
+                relation.favorite = true;
+                stat.favorite += 1;
+            }
+            restore(app, (aid, stat, relation));
+
+            let mut missed = Vec::new();
+            if !result.like {
+                missed.push("like");
+            }
+            if !result.coin {
+                missed.push("coins");
+            }
+            if !result.fav {
+                missed.push("favorite");
+            }
+            app.last_error = Some(if missed.is_empty() {
+                "Liked, coined and favorited".to_string()
+            } else {
+                format!("Triple done, except: {}", missed.join(", "))
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Switches to the detail view for `bvid`, straight from the cache when possible and otherwise
+/// with a loading indicator until the background fetch lands.
+pub fn open_details(bvid: &str, app: &mut App) {
+    // Opening a related video replaces the shown one but still returns to the same parent.
+    let parent = app.detail_parent.filter(|_| app.mode == InputMode::Detail);
+    app.clear_detail();
+    app.detail_parent = parent;
+    app.mode = InputMode::Detail;
+    match app.details.get(bvid) {
+        Some(details) => app.show_details(details.clone()),
+        None => {
+            app.detail_loading = Some(bvid.to_string());
+            app.fetch_details(bvid);
+        }
+    }
+}
+
+/// Fetches the details of the results next to the selected one, so stepping to them is instant.
+pub fn prefetch_neighbors(app: &mut App) {
+    let Some(selected) = app.results_list_state.selected() else {
+        return;
+    };
+    let neighbors = [selected.checked_sub(1), Some(selected + 1)];
+    for i in neighbors.into_iter().flatten() {
+        if let Some(video) = app.search_results.get(i) {
+            let bvid = video.bvid.clone();
+            app.fetch_details(&bvid);
+        }
+    }
+}
+
+/// Runs a search in the background and shows its results once they arrive.
+pub fn search(query: String, app: &mut App) {
+    app.tasks.spawn(Some(Slot::View), "Searching", async move {
+        let videos = api::search(&query).await.map_err(|e| e.to_string())?;
+        Ok(results(videos, ResultsSource::Search))
+    });
+}
+
+/// Loads the uploader view for `mid` on the first page of their latest videos.
+async fn load_uploader(mid: u64) -> Result<AppEvent, String> {
+    let profile = api::get_user_profile(mid)
+        .await
+        .map_err(|e| e.to_string())?;
+    let order = api::SpaceOrder::Latest;
+    let page = api::get_space_videos(mid, 1, order)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut view = UploaderView {
+        mid,
+        profile,
+        videos: page.videos,
+        list_state: Default::default(),
+        page: 1,
+        total: page.total,
+        order,
+    };
+    if !view.videos.is_empty() {
+        view.list_state.select(Some(0));
+    }
+    Ok(AppEvent::Uploader(view))
+}
+
+/// Reloads the uploader view's video list with a different page or sort order.
+pub fn load_uploader_page(page: u32, order: api::SpaceOrder, app: &mut App) {
+    let Some(view) = app.uploader.as_ref() else {
+        return;
+    };
+    let mid = view.mid;
+    app.tasks
+        .spawn(Some(Slot::View), "Loading uploads", async move {
+            let videos = api::get_space_videos(mid, page, order)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(AppEvent::UploaderPage {
+                mid,
+                page,
+                order,
+                videos,
+            })
+        });
+}
+
+/// Loads a page of a favorites folder into the results list.
+pub fn open_fav_folder(folder: api::FavFolder, page: u32, keyword: String, app: &mut App) {
+    spawn_fav_page(folder, page, keyword, true, app);
+}
+
+fn spawn_fav_page(folder: api::FavFolder, page: u32, keyword: String, focus: bool, app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::View), "Loading folder", async move {
+            let result = api::get_fav_videos(folder.id, page, &keyword)
+                .await
+                .map_err(|e| e.to_string())?;
+            let source = ResultsSource::Favorites(FavoritesPage {
+                folder,
+                page,
+                has_more: result.has_more,
+                keyword,
+            });
+            Ok(AppEvent::Results {
+                videos: result.videos,
+                source,
+                focus,
+            })
+        });
+}
+
+/// Fetches the account's folder list for an open favorites view.
+async fn fetch_fav_folders(message: Option<String>) -> Result<AppEvent, String> {
+    let mid = api::current_user_mid().await.map_err(|e| e.to_string())?;
+    let folders = api::get_fav_folders(mid, None)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(AppEvent::FavFoldersChanged { folders, message })
+}
+
+/// Opens the folder picker popup for `bvid`, pre-checking the folders that already hold it.
+pub fn open_folder_picker(bvid: &str, action: FolderPickerAction, app: &mut App) {
+    let bvid = bvid.to_string();
+    app.tasks
+        .spawn(Some(Slot::Popup), "Loading folders", async move {
+            let info = api::get_video_info(&bvid)
+                .await
+                .map_err(|e| e.to_string())?;
+            let mid = api::current_user_mid().await.map_err(|e| e.to_string())?;
+            let folders = api::get_fav_folders(mid, Some(info.aid))
+                .await
+                .map_err(|e| e.to_string())?;
+            if folders.is_empty() {
+                return Err("You have no favorites folders; create one with :fav-new".to_string());
+            }
+
+            let mut picker = FolderPicker {
+                action,
+                aid: info.aid,
+                mid,
+                checked: folders.iter().map(|f| f.fav_state == 1).collect(),
+                folders,
+                list_state: Default::default(),
+            };
+            picker.list_state.select(Some(0));
+            Ok(AppEvent::FolderPicker(picker))
+        });
+}
+
+/// Closes the folder picker and saves its choice, reloading an open favorites folder after.
+pub fn apply_folder_picker(app: &mut App) {
+    let Some(picker) = app.folder_picker.take() else {
+        return;
+    };
+    match picker.action {
+        FolderPickerAction::Favorite => {
+            let (add, del) = picker.changes();
+            if add.is_empty() && del.is_empty() {
+                return;
+            }
+            let favorited = picker.checked.iter().any(|&checked| checked);
+            app.tasks.spawn(None, "Saving favorites", async move {
+                api::deal_favorite(picker.aid, &add, &del)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(AppEvent::FavoritesSaved {
+                    aid: picker.aid,
+                    favorited: Some(favorited),
+                    message: "Favorites updated".to_string(),
+                })
+            });
+        }
+        FolderPickerAction::Move { from } | FolderPickerAction::Copy { from } => {
+            let Some(target) = picker
+                .list_state
+                .selected()
+                .and_then(|i| picker.folders.get(i))
+            else {
+                return;
+            };
+            let copy = matches!(picker.action, FolderPickerAction::Copy { .. });
+            let (target_id, title) = (target.id, target.title.clone());
+            app.tasks.spawn(None, "Saving favorites", async move {
+                api::transfer_favorite(from, target_id, picker.mid, picker.aid, copy)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                Ok(AppEvent::FavoritesSaved {
+                    aid: picker.aid,
+                    favorited: None,
+                    message: format!("{} to {}", if copy { "Copied" } else { "Moved" }, title),
+                })
+            });
+        }
+    }
+}
+
+/// Unix timestamp of local midnight at the start of `date`.
+fn local_timestamp(date: NaiveDate) -> i64 {
+    date.and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map_or(0, |midnight| midnight.timestamp())
+}
+
+/// Appends the next page of watch history, dropping entries outside the date filter.
+pub fn load_history_page(app: &mut App) {
+    spawn_history_page(false, app);
+}
+
+fn spawn_history_page(open: bool, app: &mut App) {
+    let Some(view) = app.history.as_ref() else {
+        return;
+    };
+    let cursor = view.next;
+    app.tasks
+        .spawn(Some(Slot::View), "Loading history", async move {
+            let page = api::get_history(cursor).await.map_err(|e| e.to_string())?;
+            Ok(AppEvent::HistoryPage { page, open })
+        });
+}
+
+/// Removes the selected entry from the account's watch history.
+pub fn delete_history_entry(app: &mut App) {
+    let Some(view) = app.history.as_ref() else {
+        return;
+    };
+    let Some(index) = view.list_state.selected() else {
+        return;
+    };
+    let aid = view.entries[index].history.oid;
+    app.tasks.spawn(None, "Deleting history entry", async move {
+        api::delete_history(aid).await.map_err(|e| e.to_string())?;
+        Ok(AppEvent::HistoryDeleted(aid))
+    });
+}
+
+pub fn add_to_watch_later(bvid: &str, app: &mut App) {
+    let bvid = bvid.to_string();
+    app.tasks.spawn(None, "Adding to Watch Later", async move {
+        api::add_watch_later(&bvid)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(AppEvent::Done("Added to Watch Later".to_string()))
+    });
+}
+
+/// Removes the selected video from Watch Later and from the list on screen.
+pub fn remove_from_watch_later(app: &mut App) {
+    let Some(index) = app.results_list_state.selected() else {
+        return;
+    };
+    let aid = app.search_results[index].aid;
+    app.tasks
+        .spawn(None, "Removing from Watch Later", async move {
+            api::remove_watch_later(aid)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(AppEvent::WatchLaterRemoved(aid))
+        });
+}
+
+pub fn clear_watched_watch_later(app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::View), "Clearing watched videos", async {
+            api::clear_watched_watch_later()
+                .await
+                .map_err(|e| e.to_string())?;
+            let videos = api::get_watch_later().await.map_err(|e| e.to_string())?;
+            Ok(results(videos, ResultsSource::WatchLater))
+        });
+}
+
+/// Appends the next page of the followed-uploaders feed.
+pub fn load_feed_page(app: &mut App) {
+    spawn_feed_page(false, app);
+}
+
+fn spawn_feed_page(open: bool, app: &mut App) {
+    let Some(view) = app.feed.as_ref() else {
+        return;
+    };
+    let offset = view.offset.clone();
+    app.tasks
+        .spawn(Some(Slot::View), "Loading feed", async move {
+            let page = api::get_feed(&offset).await.map_err(|e| e.to_string())?;
+            let last_seen = open.then(storage::feed_last_seen);
+            Ok(AppEvent::FeedPage { page, last_seen })
+        });
+}
+
+/// Shows `results` from `source` in the results list and starts navigating it.
+fn show_results(results: Vec<api::VideoResult>, source: ResultsSource, app: &mut App) {
+    app.set_results(results, source);
+    app.mode = InputMode::ListNav;
+    app.focused_panel = Focusable::Results;
+}
+
+/// An event bringing `videos` on screen as the results list.
+fn results(videos: Vec<api::VideoResult>, source: ResultsSource) -> AppEvent {
+    AppEvent::Results {
+        videos,
+        source,
+        focus: true,
+    }
+}
+
+/// Drops the result at `index` from the list, keeping a neighbour selected.
+fn remove_result(index: usize, app: &mut App) {
+    app.search_results.remove(index);
+    app.results_list_state
+        .select(if app.search_results.is_empty() {
+            None
+        } else {
+            Some(index.min(app.search_results.len() - 1))
+        });
+}
+
+fn load_popular(page: u32, app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::View), "Loading popular", async move {
+            let result = api::get_popular(page).await.map_err(|e| e.to_string())?;
+            let source = ResultsSource::Popular {
+                page,
+                no_more: result.no_more,
+            };
+            Ok(results(result.videos, source))
+        });
+}
+
+async fn fetch_weekly(number: u32, latest: u32) -> Result<AppEvent, String> {
+    let (issue, videos) = api::get_weekly(number).await.map_err(|e| e.to_string())?;
+    Ok(results(videos, ResultsSource::Weekly { issue, latest }))
+}
+
+fn load_ranking(zone: usize, app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::View), "Loading ranking", async move {
+            let videos = api::get_ranking(api::RANK_ZONES[zone])
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(results(videos, ResultsSource::Ranking { zone }))
+        });
+}
+
+/// Loads the tag page starting at the last of `starts`.
+fn load_tag(name: String, starts: Vec<u32>, app: &mut App) {
+    let start = starts.last().copied().unwrap_or(1);
+    app.tasks
+        .spawn(Some(Slot::View), "Loading tag", async move {
+            let page = api::search_tag(&name, start)
+                .await
+                .map_err(|e| e.to_string())?;
+            let source = ResultsSource::Tag {
+                name,
+                starts,
+                next: page.next,
+            };
+            Ok(results(page.videos, source))
+        });
+}
+
+/// Moves the results list to the next (or previous) page, issue or ranking zone.
+pub fn change_page(forward: bool, app: &mut App) {
+    match &app.results_source {
+        ResultsSource::Favorites(fav) => {
+            let page = match forward {
+                true if fav.has_more => fav.page + 1,
+                false if fav.page > 1 => fav.page - 1,
+                _ => return,
+            };
+            let (folder, keyword) = (fav.folder.clone(), fav.keyword.clone());
+            open_fav_folder(folder, page, keyword, app)
+        }
+        &ResultsSource::Popular { page, no_more } => {
+            let page = match forward {
+                true if !no_more => page + 1,
+                false if page > 1 => page - 1,
+                _ => return,
+            };
+            load_popular(page, app)
+        }
+        ResultsSource::Weekly { issue, latest } => {
+            let number = match forward {
+                true if issue.number < *latest => issue.number + 1,
+                false if issue.number > 1 => issue.number - 1,
+                _ => return,
+            };
+            let latest = *latest;
+            app.tasks.spawn(
+                Some(Slot::View),
+                "Loading weekly",
+                fetch_weekly(number, latest),
+            );
+        }
+        &ResultsSource::Ranking { zone } => {
+            let count = api::RANK_ZONES.len();
+            let zone = if forward {
+                (zone + 1) % count
+            } else {
+                (zone + count - 1) % count
+            };
+            load_ranking(zone, app)
+        }
+        ResultsSource::Tag { name, starts, next } => {
+            let mut starts = starts.clone();
+            match (forward, *next) {
+                (true, Some(next)) => starts.push(next),
+                (false, _) if starts.len() > 1 => {
+                    starts.pop();
+                }
+                _ => return,
+            }
+            load_tag(name.clone(), starts, app)
+        }
+        ResultsSource::Recommended(_) if forward => load_recommendations(app),
+        ResultsSource::Search | ResultsSource::WatchLater | ResultsSource::Recommended(_) => {}
+    }
+}
+
+/// Appends the next batch of homepage recommendations to the results list.
+fn load_recommendations(app: &mut App) {
+    let ResultsSource::Recommended(feed) = &app.results_source else {
+        return;
+    };
+    let fresh_idx = feed.fresh_idx + 1;
+    app.tasks
+        .spawn(Some(Slot::View), "Loading recommendations", async move {
+            let batch = api::get_recommendations(fresh_idx)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(AppEvent::Recommendations { fresh_idx, batch })
+        });
+}
+
+/// Removes the selected recommendation and tells the API why it was unwanted.
+pub fn dislike_recommendation(reason: api::DislikeReason, app: &mut App) {
+    let ResultsSource::Recommended(feed) = &app.results_source else {
+        return;
+    };
+    let Some(index) = app.results_list_state.selected() else {
+        return;
+    };
+    let bvid = app.search_results[index].bvid.clone();
+    let Some(target) = feed.targets.get(&bvid).cloned() else {
+        return;
+    };
+    app.tasks.spawn(None, "Sending feedback", async move {
+        api::dislike_recommendation(&target, reason)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(AppEvent::Disliked(bvid))
+    });
+}
+
+async fn fetch_live_rooms(tab: &LiveTab) -> Result<Vec<api::LiveRoom>, String> {
+    match tab {
+        LiveTab::Following => api::get_followed_live_rooms().await,
+        LiveTab::Area(area) => api::get_area_live_rooms(area.id, 1).await,
+    }
+    .map_err(|e| e.to_string())
+}
+
+/// Switches the live view to tab `tab` and lists its rooms.
+pub fn load_live_tab(tab: usize, app: &mut App) {
+    let Some(selected) = app
+        .live
+        .as_ref()
+        .and_then(|view| view.tabs.get(tab))
+        .cloned()
+    else {
+        return;
+    };
+    app.tasks
+        .spawn(Some(Slot::View), "Loading live rooms", async move {
+            let rooms = fetch_live_rooms(&selected).await?;
+            Ok(AppEvent::LiveRooms { tab, rooms })
+        });
+}
+
+/// Opens the quality picker for a live room, failing if it is offline.
+pub fn open_quality_picker(room_id: u64, app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::Popup), "Loading room", async move {
+            let room = api::get_live_room(room_id)
+                .await
+                .map_err(|e| e.to_string())?;
+            if room.live_status != 1 {
+                return Err(format!("{} is not live right now", room.title));
+            }
+            let stream = api::get_live_play_url(room.room_id, 10000)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            let mut picker = QualityPicker {
+                room_id: room.room_id,
+                qualities: stream.qualities,
+                list_state: Default::default(),
+            };
+            picker.list_state.select(Some(0));
+            Ok(AppEvent::QualityPicker(picker))
+        });
+}
+
+/// Plays the room in the quality picker at the highlighted quality and closes it.
+pub fn play_picked_quality(app: &mut App) {
+    let Some(picker) = app.quality_picker.take() else {
+        return;
+    };
+    let qn = picker
+        .list_state
+        .selected()
+        .and_then(|i| picker.qualities.get(i))
+        .map_or(10000, |quality| quality.qn);
+    let room_id = picker.room_id;
+    app.tasks.spawn(None, "Starting stream", async move {
+        let stream = api::get_live_play_url(room_id, qn)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(AppEvent::PlayLive {
+            room_id,
+            url: stream.url,
+        })
+    });
+}
+
+/// Starts recording a live room in the background and shows the Recordings panel.
+///
+/// The room doesn't have to be live yet; the recording waits for the broadcast to start.
+pub fn start_recording(room_id: u64, app: &mut App) {
+    app.tasks.spawn(None, "Starting recording", async move {
+        let room = api::get_live_room(room_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(AppEvent::RecordRoom(room))
+    });
+}
+
+/// Loads a season into the season view, selecting the episode when opened by an `ep…` id.
+pub fn open_season(id: api::SeasonId, app: &mut App) {
+    app.tasks
+        .spawn(Some(Slot::View), "Loading season", async move {
+            let season = api::get_season(id).await.map_err(|e| e.to_string())?;
+            let episode = match id {
+                api::SeasonId::Episode(ep_id) => Some(ep_id),
+                api::SeasonId::Season(_) => None,
+            };
+            Ok(AppEvent::Season { season, episode })
+        });
+}
+
+/// Plays the selected episode after checking that it isn't region- or membership-locked.
+pub fn play_episode(app: &mut App) {
+    let Some(episode) = app
+        .season
+        .as_ref()
+        .and_then(|view| view.selected())
+        .cloned()
+    else {
+        return;
+    };
+    app.tasks.spawn(None, "Checking episode", async move {
+        api::check_episode_playable(&episode)
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(AppEvent::PlayEpisode(episode))
+    });
+}
+
+/// Applies `update` to the detail view's counters and relation ahead of the request, returning
+/// the video's aid and the state to restore should the request fail.
+fn update_optimistically(
+    app: &mut App,
+    update: impl FnOnce(&mut api::Stat, &mut api::Relation),
+) -> Result<(u64, api::Stat, api::Relation), String> {
+    let Some(info) = app.video_info.as_mut() else {
+        return Err("The video's details are still loading".to_string());
+    };
+    let Some(relation) = app.relation.as_mut() else {
+        return Err("Log in (set BILI_COOKIE) to like or give coins".to_string());
+    };
+    let snapshot = (info.aid, info.stat.clone(), relation.clone());
+    update(&mut info.stat, relation);
+    Ok(snapshot)
+}
+
+/// Puts back the state saved by [`update_optimistically`] if the same video is still shown.
+fn restore(app: &mut App, (aid, stat, relation): (u64, api::Stat, api::Relation)) {
+    if let Some(info) = app.video_info.as_mut().filter(|info| info.aid == aid) {
+        info.stat = stat;
+        app.relation = Some(relation);
+    }
+}
+
+/// An event confirming an optimistic update, or undoing it if the request failed.
+fn confirm(
+    snapshot: (u64, api::Stat, api::Relation),
+    result: Result<(), Box<dyn std::error::Error + Send + Sync>>,
+    message: String,
+) -> Result<AppEvent, String> {
+    Ok(match result {
+        Ok(()) => AppEvent::Done(message),
+        Err(e) => AppEvent::InteractionFailed {
+            snapshot,
+            error: e.to_string(),
+        },
+    })
+}
+
+fn like(stat: &mut api::Stat, relation: &mut api::Relation) {
+    if !relation.like {
+        relation.like = true;
+        stat.like += 1;
+    }
+}
+
+/// Likes the video in the detail view, or takes an existing like back.
+pub fn toggle_like(app: &mut App) -> Result<(), String> {
+    let liked = app.relation.as_ref().is_some_and(|relation| relation.like);
+    let snapshot = update_optimistically(app, |stat, relation| {
+        if liked {
+            relation.like = false;
+            stat.like = stat.like.saturating_sub(1);
+        } else {
+            like(stat, relation);
+        }
+    })?;
+    let message = if liked { "Like removed" } else { "Liked" }.to_string();
+    app.tasks.spawn(None, "Liking", async move {
+        let result = api::like_video(snapshot.0, !liked).await;
+        confirm(snapshot, result, message)
+    });
+    Ok(())
+}
+
+/// Gives the video in the detail view `coins` coins, liking it too as the web player does.
+pub fn give_coins(coins: u8, app: &mut App) -> Result<(), String> {
+    let given = app.relation.as_ref().map_or(0, |relation| relation.coin);
+    if let Some(max_coins) = app.video_info.as_ref().map(api::VideoInfo::max_coins)
+        && given + coins > max_coins
+    {
+        return Err(format!(
+            "Already gave {} of {} coins to this video",
+            given, max_coins
+        ));
+    }
+    let also_like = app.relation.as_ref().is_some_and(|relation| !relation.like);
+    let snapshot = update_optimistically(app, |stat, relation| {
+        relation.coin += coins;
+        stat.coin += u64::from(coins);
+        like(stat, relation);
+    })?;
+    let message = format!("Gave {} coin{}", coins, if coins == 1 { "" } else { "s" });
+    app.tasks.spawn(None, "Giving coins", async move {
+        let result = api::add_coin(snapshot.0, coins, also_like).await;
+        confirm(snapshot, result, message)
+    });
+    Ok(())
+}
+
+/// Likes, coins and favorites the video in the detail view (一键三连).
+pub fn triple(app: &mut App) -> Result<(), String> {
+    let max_coins = app.video_info.as_ref().map_or(0, api::VideoInfo::max_coins);
+    let snapshot = update_optimistically(app, |stat, relation| {
+        like(stat, relation);
+        stat.coin += u64::from(max_coins.saturating_sub(relation.coin));
+        relation.coin = relation.coin.max(max_coins);
+        if !relation.favorite {
+            relation.favorite = true;
+            stat.favorite += 1;
         }
+    })?;
+    app.tasks
+        .spawn(None, "Liking, coining and favoriting", async move {
+            Ok(match api::triple(snapshot.0).await {
+                Ok(result) => AppEvent::Tripled { snapshot, result },
+                Err(e) => AppEvent::InteractionFailed {
+                    snapshot,
+                    error: e.to_string(),
+                },
+            })
+        });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{detail_app, video};
+    use chrono::{DateTime, Timelike};
+
+    #[test]
+    fn parses_history_dates() {
+        assert_eq!(parse(":history"), Ok(Command::ShowHistory(None)));
+        assert_eq!(
+            parse(":history 2024-02-29"),
+            Ok(Command::ShowHistory(NaiveDate::from_ymd_opt(2024, 2, 29)))
+        );
+        assert!(parse(":history 2023-02-29").is_err());
+        assert!(parse(":history yesterday").is_err());
+        assert!(parse(":history 2024-02-29 2024-03-01").is_err());
+    }
+
+    #[test]
+    fn finds_local_midnight() {
+        let date = NaiveDate::from_ymd_opt(2024, 2, 29).unwrap();
+        let midnight = DateTime::from_timestamp(local_timestamp(date), 0)
+            .unwrap()
+            .with_timezone(&Local);
+        assert_eq!(midnight.date_naive(), date);
+        assert_eq!(
+            (midnight.hour(), midnight.minute(), midnight.second()),
+            (0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn keeps_a_neighbour_selected_when_removing_a_result() {
+        let mut app = App::new();
+        app.set_results(
+            vec![video("BV1"), video("BV2"), video("BV3")],
+            ResultsSource::WatchLater,
+        );
+
+        remove_result(1, &mut app);
+        assert_eq!(app.results_list_state.selected(), Some(1));
+        assert_eq!(app.selected_result().map(|v| v.bvid.as_str()), Some("BV3"));
+
+        remove_result(1, &mut app);
+        assert_eq!(app.selected_result().map(|v| v.bvid.as_str()), Some("BV1"));
+
+        remove_result(0, &mut app);
+        assert_eq!(app.results_list_state.selected(), None);
+    }
+
+    #[test]
+    fn parses_weekly_issues_and_rank_zones() {
+        assert_eq!(parse(":weekly"), Ok(Command::ShowWeekly(None)));
+        assert_eq!(parse(":weekly 42"), Ok(Command::ShowWeekly(Some(42))));
+        assert!(parse(":weekly latest").is_err());
+        assert_eq!(parse(":rank"), Ok(Command::ShowRanking(0)));
+        assert_eq!(parse(":rank 音乐"), Ok(Command::ShowRanking(2)));
+        assert!(
+            parse(":rank nope")
+                .is_err_and(|e| e.starts_with("Unknown zone nope; try one of: all, anime"))
+        );
+    }
+
+    #[test]
+    fn parses_live_room_ids() {
+        assert_eq!(parse(":room 21452505"), Ok(Command::PlayLiveRoom(21452505)));
+        assert!(parse(":room").is_err());
+        assert!(parse(":room abc").is_err());
+    }
+
+    #[test]
+    fn extracts_season_ids_from_ids_and_urls() {
+        use api::SeasonId::{Episode, Season};
+        assert_eq!(extract_season_id("ss28747"), Some(Season(28747)));
+        assert_eq!(extract_season_id("ep374717"), Some(Episode(374717)));
+        assert_eq!(
+            extract_season_id("https://www.bilibili.com/bangumi/play/ss28747"),
+            Some(Season(28747))
+        );
+        assert_eq!(
+            extract_season_id("https://m.bilibili.com/bangumi/play/ep374717/?spm_id_from=333"),
+            Some(Episode(374717))
+        );
+
+        assert_eq!(extract_season_id("ss"), None);
+        assert_eq!(extract_season_id("ssabc"), None);
+        assert_eq!(extract_season_id("md28747"), None);
+        assert_eq!(extract_season_id("BV17x411w7KC"), None);
+        assert_eq!(
+            extract_season_id("https://example.com/bangumi/play/ss28747"),
+            None
+        );
+        assert_eq!(
+            extract_season_id("https://www.bilibili.com/video/ss28747"),
+            None
+        );
+        assert_eq!(
+            extract_season_id("https://www.bilibili.com/bangumi/media/md28747"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_season_commands() {
+        assert_eq!(
+            parse(":season ss28747"),
+            Ok(Command::ShowSeason(api::SeasonId::Season(28747)))
+        );
+        assert!(parse(":season").is_err());
+        assert!(parse(":season 28747").is_err());
+    }
+
+    /// An app showing the details of video `aid`, with the user's `relation` to it.
+    fn counters(app: &App) -> (u64, u64, u64) {
+        let stat = &app.video_info.as_ref().unwrap().stat;
+        (stat.like, stat.coin, stat.favorite)
+    }
+
+    #[test]
+    fn needs_loaded_details_and_a_login_to_interact() {
+        let mut app = App::new();
+        assert_eq!(
+            update_optimistically(&mut app, like).err().as_deref(),
+            Some("The video's details are still loading")
+        );
+        let mut app = detail_app(1, None);
+        assert!(update_optimistically(&mut app, like).is_err_and(|e| e.starts_with("Log in")));
+    }
+
+    #[test]
+    fn rolls_an_optimistic_update_back_when_the_request_fails() {
+        let mut app = detail_app(1, Some(api::Relation::default()));
+        let snapshot = update_optimistically(&mut app, like).unwrap();
+        assert_eq!(counters(&app), (11, 5, 3));
+        assert!(app.relation.as_ref().unwrap().like);
+
+        let failed = AppEvent::InteractionFailed {
+            snapshot,
+            error: "Request failed".to_string(),
+        };
+        assert_eq!(apply(failed, &mut app), Err("Request failed".to_string()));
+        assert_eq!(counters(&app), (10, 5, 3));
+        assert_eq!(app.relation, Some(api::Relation::default()));
+    }
+
+    #[test]
+    fn leaves_another_video_alone_when_rolling_back() {
+        let mut app = detail_app(1, Some(api::Relation::default()));
+        let snapshot = update_optimistically(&mut app, like).unwrap();
+        let mut app_on_another_video = detail_app(2, Some(api::Relation::default()));
+        restore(&mut app_on_another_video, snapshot);
+        assert_eq!(counters(&app_on_another_video), (10, 5, 3));
+        assert_eq!(
+            app_on_another_video.relation,
+            Some(api::Relation::default())
+        );
+    }
+
+    #[test]
+    fn refuses_coins_beyond_the_limit() {
+        let relation = api::Relation {
+            coin: 1,
+            ..Default::default()
+        };
+        let mut app = detail_app(1, Some(relation));
+        assert_eq!(
+            give_coins(2, &mut app),
+            Err("Already gave 1 of 2 coins to this video".to_string())
+        );
+        assert_eq!(counters(&app), (10, 5, 3));
+    }
+
+    #[test]
+    fn gives_a_repost_at_most_one_coin() {
+        let mut app = detail_app(1, Some(api::Relation::default()));
+        app.video_info.as_mut().unwrap().copyright = 2;
+        assert_eq!(
+            give_coins(2, &mut app),
+            Err("Already gave 0 of 1 coins to this video".to_string())
+        );
+        assert_eq!(counters(&app), (10, 5, 3));
+
+        triple(&mut app).unwrap();
+        assert_eq!(counters(&app), (11, 6, 4));
+        assert_eq!(app.relation.as_ref().unwrap().coin, 1);
+    }
+
+    #[test]
+    fn replaces_a_triple_guess_with_what_the_server_did() {
+        let relation = api::Relation {
+            coin: 1,
+            ..Default::default()
+        };
+        let mut app = detail_app(1, Some(relation.clone()));
+        let snapshot = (1, app.video_info.as_ref().unwrap().stat.clone(), relation);
+        let result = api::TripleResult {
+            like: true,
+            coin: true,
+            fav: false,
+            multiply: 1,
+        };
+        apply(AppEvent::Tripled { snapshot, result }, &mut app).unwrap();
+        assert_eq!(counters(&app), (11, 6, 3));
+        assert_eq!(
+            app.relation,
+            Some(api::Relation {
+                like: true,
+                coin: 2,
+                favorite: false,
+            })
+        );
+        assert_eq!(
+            app.last_error.as_deref(),
+            Some("Triple done, except: favorite")
+        );
+    }
+
+    #[test]
+    fn stays_put_after_the_login_check_unless_on_the_start_screen() {
+        let mut app = App::new();
+        apply(AppEvent::LoginChecked { logged_in: false }, &mut app).unwrap();
+        assert_eq!(app.mode, InputMode::Start);
+        assert!(app.tasks.busy().is_none());
+
+        app.mode = InputMode::Help;
+        apply(AppEvent::LoginChecked { logged_in: true }, &mut app).unwrap();
+        assert_eq!(app.mode, InputMode::Help);
+        assert!(app.tasks.busy().is_none());
     }
 }
@@ -0,0 +1,491 @@
+use crate::{api, storage};
+use base64::Engine;
+use image::{RgbImage, imageops::FilterType};
+use ratatui::{layout::Rect, style::Style, text::Line, text::Span};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+/// Decoded covers kept in memory; the on-disk cache backs it across runs.
+static MEMORY: Mutex<Memory> = Mutex::new(Memory::new());
+const MEMORY_ENTRIES: usize = 64;
+/// How much the on-disk cache may hold before the least recently written covers go.
+const DISK_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Decoded covers by URL, oldest first, for eviction.
+struct Memory {
+    covers: VecDeque<(String, Arc<RgbImage>)>,
+}
+
+impl Memory {
+    const fn new() -> Self {
+        Self {
+            covers: VecDeque::new(),
+        }
+    }
+
+    fn get(&self, pic: &str) -> Option<Arc<RgbImage>> {
+        self.covers
+            .iter()
+            .find(|(url, _)| url == pic)
+            .map(|(_, image)| image.clone())
+    }
+
+    fn insert(&mut self, pic: &str, image: Arc<RgbImage>) {
+        if self.get(pic).is_some() {
+            return;
+        }
+        if self.covers.len() >= MEMORY_ENTRIES {
+            self.covers.pop_front();
+        }
+        self.covers.push_back((pic.to_string(), image));
+    }
+}
+
+/// Cell size in pixels to assume when the terminal doesn't report its own.
+const DEFAULT_CELL: (u32, u32) = (10, 20);
+
+/// How cover images are drawn into the terminal.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Protocol {
+    Kitty,
+    Iterm,
+    Sixel,
+    /// Two pixels per cell using `▀` with separate foreground and background colors.
+    HalfBlocks,
+}
+
+impl Protocol {
+    /// Picks a protocol from `BILI_TUI_GRAPHICS` or, failing that, from what the terminal says it is.
+    pub fn detect() -> Self {
+        let var = |name| std::env::var(name).unwrap_or_default();
+        match var("BILI_TUI_GRAPHICS").as_str() {
+            "kitty" => return Self::Kitty,
+            "iterm" => return Self::Iterm,
+            "sixel" => return Self::Sixel,
+            "halfblocks" => return Self::HalfBlocks,
+            _ => {}
+        }
+        let term = var("TERM");
+        let program = var("TERM_PROGRAM");
+        if std::env::var_os("KITTY_WINDOW_ID").is_some()
+            || term == "xterm-kitty"
+            || term == "xterm-ghostty"
+            || program == "ghostty"
+        {
+            Self::Kitty
+        } else if program == "iTerm.app" || program == "WezTerm" {
+            Self::Iterm
+        } else if term.contains("sixel") || term.starts_with("foot") || term.starts_with("mlterm") {
+            Self::Sixel
+        } else {
+            Self::HalfBlocks
+        }
+    }
+}
+
+/// A decoded cover and the URL it came from.
+#[derive(Clone)]
+pub struct Cover {
+    pub url: String,
+    pub image: Arc<RgbImage>,
+}
+
+/// The cover shown in the detail view and where, if anywhere, it was last drawn.
+pub struct CoverState {
+    pub protocol: Protocol,
+    pub current: Option<Cover>,
+    /// Where the UI wants the cover this frame; set while drawing, consumed by [`CoverState::present`].
+    pub area: Option<Rect>,
+    drawn: Option<(String, Rect)>,
+}
+
+impl CoverState {
+    pub fn new() -> Self {
+        Self {
+            protocol: Protocol::detect(),
+            current: None,
+            area: None,
+            drawn: None,
+        }
+    }
+
+    /// Writes the graphics escape sequences for this frame's cover, if it changed since the last one.
+    ///
+    /// Returns true when an image was taken away that only a full repaint can erase.
+    pub fn present(&mut self, out: &mut impl Write) -> io::Result<bool> {
+        let wanted = self.area.take().zip(self.current.as_ref());
+        let key = wanted.map(|(area, cover)| (cover.url.clone(), area));
+        if self.protocol == Protocol::HalfBlocks || key == self.drawn {
+            return Ok(false);
+        }
+
+        let mut repaint = false;
+        if self.drawn.take().is_some() {
+            match self.protocol {
+                Protocol::Kitty => write!(out, "\x1b_Ga=d,d=A,q=2\x1b\\")?,
+                _ => repaint = true,
+            }
+        }
+        // After a repaint the image has to wait for the next frame, or the clear would wipe it.
+        if let Some((area, cover)) = wanted.filter(|_| !repaint) {
+            let sequence = match self.protocol {
+                Protocol::Kitty => kitty(&cover.image, area),
+                Protocol::Iterm => iterm(&cover.image, area),
+                Protocol::Sixel => sixel(&cover.image, area),
+                Protocol::HalfBlocks => unreachable!(),
+            };
+            crossterm::queue!(out, crossterm::cursor::MoveTo(area.x, area.y))?;
+            out.write_all(sequence.as_bytes())?;
+            out.flush()?;
+            self.drawn = key;
+        }
+        Ok(repaint)
+    }
+}
+
+/// Asks the image CDN for a thumbnail instead of the full-size cover.
+fn thumbnail_url(pic: &str) -> String {
+    let url = if let Some(rest) = pic.strip_prefix("//") {
+        format!("https://{}", rest)
+    } else {
+        pic.replacen("http://", "https://", 1)
+    };
+    format!("{}@320w_200h_1c.jpg", url)
+}
+
+/// A cover decoded earlier in this run, if it is still in memory.
+pub fn cached(pic: &str) -> Option<Arc<RgbImage>> {
+    MEMORY.lock().unwrap().get(pic)
+}
+
+/// Fetches and decodes a cover, going through the memory and disk caches first.
+pub async fn load(pic: &str) -> Result<Arc<RgbImage>, String> {
+    if pic.is_empty() {
+        return Err("the video has no cover".to_string());
+    }
+    if let Some(image) = cached(pic) {
+        return Ok(image);
+    }
+
+    let path = storage::cache_dir()
+        .map(|dir| dir.join("covers"))
+        .and_then(|dir| std::fs::create_dir_all(&dir).map(|_| dir))
+        .map(|dir| dir.join(format!("{:x}.jpg", md5::compute(pic))))
+        .ok();
+    let cached = path.as_ref().and_then(|path| std::fs::read(path).ok());
+    let bytes = match cached {
+        Some(bytes) => bytes,
+        None => {
+            let bytes = api::get_image(&thumbnail_url(pic))
+                .await
+                .map_err(|e| e.to_string())?;
+            if let Some(path) = &path {
+                // The cache is only an optimisation, so failing to write or prune it is fine.
+                let _ = std::fs::write(path, &bytes);
+                if let Some(dir) = path.parent() {
+                    let _ = prune(dir, DISK_BYTES);
+                }
+            }
+            bytes
+        }
+    };
+    // Decoding takes long enough to stall the event loop, so it gets a blocking thread.
+    let decode = move || image::load_from_memory(&bytes).map(|image| image.to_rgb8());
+    let image = tokio::task::spawn_blocking(decode)
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("failed to decode cover: {}", e))?;
+    let image = Arc::new(image);
+
+    MEMORY.lock().unwrap().insert(pic, image.clone());
+    Ok(image)
+}
+
+/// Deletes the least recently written files in `dir` until the rest fit in `max_bytes`.
+fn prune(dir: &Path, max_bytes: u64) -> io::Result<()> {
+    let mut files: Vec<(SystemTime, u64, std::path::PathBuf)> = std::fs::read_dir(dir)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok().filter(|m| m.is_file())?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    files.sort_by_key(|&(modified, _, _)| std::cmp::Reverse(modified));
+
+    let mut total = 0;
+    for (_, len, path) in files {
+        total += len;
+        if total > max_bytes {
+            std::fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// The terminal's cell size in pixels.
+fn cell_size() -> (u32, u32) {
+    crossterm::terminal::window_size()
+        .ok()
+        .filter(|size| size.columns > 0 && size.rows > 0)
+        .filter(|size| size.width >= size.columns && size.height >= size.rows)
+        .map_or(DEFAULT_CELL, |size| {
+            (
+                u32::from(size.width / size.columns),
+                u32::from(size.height / size.rows),
+            )
+        })
+}
+
+/// The largest `(columns, rows)` an image fits in within `area` keeping its aspect ratio.
+fn fit(image: &RgbImage, area: Rect, (cell_w, cell_h): (u32, u32)) -> (u16, u16) {
+    let (width, height) = image.dimensions();
+    let max_w = u32::from(area.width) * cell_w;
+    let max_h = u32::from(area.height) * cell_h;
+    let (fit_w, fit_h) = if width * max_h > height * max_w {
+        (max_w, height * max_w / width.max(1))
+    } else {
+        (width * max_h / height.max(1), max_h)
+    };
+    (
+        (fit_w / cell_w).clamp(1, u32::from(area.width.max(1))) as u16,
+        (fit_h / cell_h).clamp(1, u32::from(area.height.max(1))) as u16,
+    )
+}
+
+fn png(image: &RgbImage) -> Vec<u8> {
+    let mut png = Vec::new();
+    image
+        .write_to(&mut io::Cursor::new(&mut png), image::ImageFormat::Png)
+        .expect("encoding PNG into memory cannot fail");
+    png
+}
+
+/// Transmits the image as PNG with the kitty graphics protocol, in 4096-byte chunks.
+fn kitty(image: &RgbImage, area: Rect) -> String {
+    let (cols, rows) = fit(image, area, cell_size());
+    let data = base64::engine::general_purpose::STANDARD.encode(png(image));
+    let chunks: Vec<&[u8]> = data.as_bytes().chunks(4096).collect();
+    let mut out = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let more = u8::from(i + 1 < chunks.len());
+        let chunk = std::str::from_utf8(chunk).unwrap_or_default();
+        if i == 0 {
+            let _ = write!(
+                out,
+                "\x1b_Ga=T,f=100,q=2,C=1,c={},r={},m={};{}\x1b\\",
+                cols, rows, more, chunk
+            );
+        } else {
+            let _ = write!(out, "\x1b_Gm={};{}\x1b\\", more, chunk);
+        }
+    }
+    out
+}
+
+/// Sends the image as an inline file, as iTerm2 and WezTerm understand it.
+fn iterm(image: &RgbImage, area: Rect) -> String {
+    let (cols, rows) = fit(image, area, cell_size());
+    let png = png(image);
+    format!(
+        "\x1b]1337;File=inline=1;size={};width={};height={};preserveAspectRatio=1:{}\x07",
+        png.len(),
+        cols,
+        rows,
+        base64::engine::general_purpose::STANDARD.encode(&png)
+    )
+}
+
+/// Encodes the image as sixels over a 6×6×6 color cube, scaled to fit `area`.
+fn sixel(image: &RgbImage, area: Rect) -> String {
+    let cell = cell_size();
+    let (cols, rows) = fit(image, area, cell);
+    let image = image::imageops::resize(
+        image,
+        u32::from(cols) * cell.0,
+        u32::from(rows) * cell.1,
+        FilterType::Triangle,
+    );
+    let (width, height) = image.dimensions();
+    let level = |c: u8| (u16::from(c) * 5 + 127) / 255;
+    let index = |p: &image::Rgb<u8>| (level(p[0]) * 36 + level(p[1]) * 6 + level(p[2])) as usize;
+
+    let mut out = format!("\x1bPq\"1;1;{};{}", width, height);
+    for i in 0..216u16 {
+        let percent = |v: u16| v * 100 / 5;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            i,
+            percent(i / 36),
+            percent(i / 6 % 6),
+            percent(i % 6)
+        );
+    }
+    for band in (0..height).step_by(6) {
+        let band_height = (height - band).min(6);
+        // Bit masks of which of the band's six rows each color covers, column by column.
+        let mut masks: BTreeMap<usize, Vec<u8>> = BTreeMap::new();
+        for x in 0..width {
+            for dy in 0..band_height {
+                let color = index(image.get_pixel(x, band + dy));
+                masks
+                    .entry(color)
+                    .or_insert_with(|| vec![0; width as usize])[x as usize] |= 1 << dy;
+            }
+        }
+        for (color, columns) in masks {
+            let _ = write!(out, "#{}", color);
+            let mut run = 0;
+            let mut previous = None;
+            for mask in columns.into_iter().map(Some).chain([None]) {
+                if mask == previous {
+                    run += 1;
+                    continue;
+                }
+                if let Some(bits) = previous {
+                    let c = char::from(63 + bits);
+                    if run > 3 {
+                        let _ = write!(out, "!{}{}", run, c);
+                    } else {
+                        out.extend(std::iter::repeat_n(c, run));
+                    }
+                }
+                previous = mask;
+                run = 1;
+            }
+            out.push('$');
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
+
+/// Renders the image with `▀`, the cell's foreground and background painting two stacked pixels.
+pub fn half_blocks(image: &RgbImage, area: Rect) -> Vec<Line<'static>> {
+    // A half-block pixel is about square, so a cell counts as one pixel wide and two high.
+    let (cols, rows) = fit(image, area, (1, 2));
+    let image = image::imageops::resize(
+        image,
+        u32::from(cols),
+        u32::from(rows) * 2,
+        FilterType::Triangle,
+    );
+    (0..u32::from(rows))
+        .map(|row| {
+            let spans: Vec<Span> = (0..u32::from(cols))
+                .map(|x| {
+                    let color = |y| {
+                        let p = image.get_pixel(x, y);
+                        ratatui::style::Color::Rgb(p[0], p[1], p[2])
+                    };
+                    Span::styled(
+                        "▀",
+                        Style::default().fg(color(row * 2)).bg(color(row * 2 + 1)),
+                    )
+                })
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(width: u32, height: u32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, _| {
+            if x < width / 2 {
+                image::Rgb([255, 0, 0])
+            } else {
+                image::Rgb([0, 0, 255])
+            }
+        })
+    }
+
+    #[test]
+    fn fits_wide_covers_by_width() {
+        let area = Rect::new(0, 0, 40, 20);
+        assert_eq!(fit(&image(320, 200), area, (10, 20)), (40, 12));
+        assert_eq!(fit(&image(100, 400), area, (10, 20)), (10, 20));
+    }
+
+    #[test]
+    fn half_blocks_stack_two_pixels_per_cell() {
+        let lines = half_blocks(&image(4, 4), Rect::new(0, 0, 4, 2));
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].spans.len(), 4);
+        assert_eq!(
+            lines[0].spans[0].style.fg,
+            Some(ratatui::style::Color::Rgb(255, 0, 0))
+        );
+        assert_eq!(
+            lines[0].spans[3].style.bg,
+            Some(ratatui::style::Color::Rgb(0, 0, 255))
+        );
+    }
+
+    #[test]
+    fn sixel_output_is_a_complete_sequence() {
+        let out = sixel(&image(20, 20), Rect::new(0, 0, 2, 1));
+        assert!(out.starts_with("\x1bPq\""));
+        assert!(out.ends_with("-\x1b\\"));
+        // Pure red and pure blue land on the cube's corners.
+        assert!(out.contains("#180;2;100;0;0"));
+        assert!(out.contains("#5;2;0;0;100"));
+    }
+
+    #[test]
+    fn evicts_the_oldest_cover_from_memory() {
+        let mut memory = Memory::new();
+        for i in 0..MEMORY_ENTRIES {
+            memory.insert(&format!("z{}", i), Arc::new(image(1, 1)));
+        }
+        // Inserting a cover again doesn't count it twice.
+        memory.insert("z0", Arc::new(image(1, 1)));
+        memory.insert("a", Arc::new(image(1, 1)));
+        assert!(memory.get("z0").is_none());
+        assert!(memory.get("z1").is_some());
+        assert!(memory.get("a").is_some());
+        assert_eq!(memory.covers.len(), MEMORY_ENTRIES);
+    }
+
+    #[test]
+    fn prunes_the_oldest_covers_from_disk() {
+        let dir = std::env::temp_dir().join(format!("bili-tui-covers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let now = SystemTime::now();
+        for (name, age) in [("old", 30), ("middle", 20), ("new", 10)] {
+            let file = std::fs::File::create(dir.join(name)).unwrap();
+            file.set_len(100).unwrap();
+            file.set_modified(now - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+
+        prune(&dir, 250).unwrap();
+        let mut left: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(left, ["middle", "new"]);
+    }
+
+    #[test]
+    fn thumbnails_are_requested_over_https() {
+        assert_eq!(
+            thumbnail_url("//i0.hdslb.com/bfs/archive/a.jpg"),
+            "https://i0.hdslb.com/bfs/archive/a.jpg@320w_200h_1c.jpg"
+        );
+        assert_eq!(
+            thumbnail_url("http://i1.hdslb.com/bfs/archive/b.jpg"),
+            "https://i1.hdslb.com/bfs/archive/b.jpg@320w_200h_1c.jpg"
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::api;
+use crate::app::{App, LiveChat, Recording};
+use crate::cache;
+use crate::storage;
+
+/// Something an update needs done outside the app, such as starting mpv or touching the cache.
+/// Updates only queue these in [`App::effects`]; the main loop carries them out with
+/// [`run_pending`], so updating the app stays free of side effects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Effect {
+    /// Starts mpv with `args`; `what` names what was to be played if mpv can't be started.
+    Play {
+        args: Vec<String>,
+        what: &'static str,
+    },
+    /// Turns offline mode on or off, or toggles it without a value.
+    SetOffline(Option<bool>),
+    ReportCacheStats,
+    ClearCache,
+    /// Remembers the publish time of the newest feed item, so the next visit can mark what's new.
+    MarkFeedSeen(i64),
+    /// Joins the live chat of a room, leaving the one open before.
+    JoinChat(u64),
+    /// Starts recording a room; `name` labels it in the recordings view.
+    Record {
+        room_id: u64,
+        name: String,
+    },
+}
+
+impl Effect {
+    /// Plays a video page, starting `start` seconds in when given.
+    pub fn play_video(bvid: &str, start: Option<i64>) -> Self {
+        let mut args: Vec<_> = start
+            .map(|start| format!("--start={}", start))
+            .into_iter()
+            .collect();
+        args.push(format!("https://www.bilibili.com/video/{}", bvid));
+        Effect::Play {
+            args,
+            what: "video",
+        }
+    }
+
+    /// Plays a live stream URL, sending the headers the live CDN expects.
+    pub fn play_live_stream(url: &str) -> Self {
+        Effect::Play {
+            args: vec![
+                format!("--referrer={}", api::LIVE_REFERER),
+                format!("--user-agent={}", api::USER_AGENT),
+                url.to_string(),
+            ],
+            what: "stream",
+        }
+    }
+}
+
+/// Carries out what the updates since the last call asked for: starts their background tasks
+/// and runs their effects, showing any that fails as the error.
+pub fn run_pending(app: &mut App) {
+    app.tasks.start_queued();
+    for effect in std::mem::take(&mut app.effects) {
+        if let Err(e) = run(effect, app) {
+            app.last_error = Some(e);
+        }
+    }
+}
+
+fn run(effect: Effect, app: &mut App) -> Result<(), String> {
+    match effect {
+        Effect::Play { args, what } => {
+            std::process::Command::new("mpv")
+                .args(args)
+                .spawn()
+                .map_err(|e| format!("Failed to play {}: {}", what, e))?;
+        }
+        Effect::SetOffline(offline) => {
+            let offline = offline.unwrap_or(!cache::is_offline());
+            cache::set_offline(offline);
+            app.last_error = Some(
+                if offline {
+                    "Offline: showing cached data only"
+                } else {
+                    "Online"
+                }
+                .to_string(),
+            );
+        }
+        Effect::ReportCacheStats => {
+            let stats = cache::stats().map_err(|e| format!("Failed to read the cache: {}", e))?;
+            app.last_error = Some(format!(
+                "Cache: {} responses, {:.1} MiB; this session {} hits, {} stale, {} misses{}",
+                stats.entries,
+                stats.bytes as f64 / (1024.0 * 1024.0),
+                stats.hits,
+                stats.stale_hits,
+                stats.misses,
+                if cache::is_offline() {
+                    " (offline)"
+                } else {
+                    ""
+                }
+            ));
+        }
+        Effect::ClearCache => {
+            let removed =
+                cache::clear().map_err(|e| format!("Failed to clear the cache: {}", e))?;
+            app.last_error = Some(format!("Removed {} cached responses", removed));
+        }
+        Effect::MarkFeedSeen(timestamp) => {
+            storage::set_feed_last_seen(timestamp).map_err(|e| e.to_string())?;
+        }
+        Effect::JoinChat(room_id) => app.live_chat = Some(LiveChat::join(room_id)),
+        Effect::Record { room_id, name } => {
+            let dir = storage::recordings_dir().map_err(|e| e.to_string())?;
+            app.recordings
+                .push(Recording::start(room_id, name, dir.clone()));
+            app.recordings_list_state
+                .select(Some(app.recordings.len() - 1));
+            app.last_error = Some(format!("Recording room {} into {}", room_id, dir.display()));
+        }
+    }
+    Ok(())
+}
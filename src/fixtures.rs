@@ -0,0 +1,68 @@
+use crate::api;
+use crate::app::{App, FolderPicker, FolderPickerAction, VideoDetails};
+use ratatui::widgets::ListState;
+
+/// A search result for `bvid` with only the fields the API always sends.
+pub fn video(bvid: &str) -> api::VideoResult {
+    serde_json::from_value(serde_json::json!({
+        "type": "video",
+        "author": "up",
+        "bvid": bvid,
+        "title": bvid,
+        "description": "",
+    }))
+    .unwrap()
+}
+
+/// A favorites folder; `fav_state` 1 means it already holds the video being favorited.
+pub fn folder(id: u64, fav_state: u8) -> api::FavFolder {
+    serde_json::from_value(serde_json::json!({
+        "id": id,
+        "title": format!("folder {}", id),
+        "media_count": 0,
+        "fav_state": fav_state,
+    }))
+    .unwrap()
+}
+
+/// A folder picker for video 170001 over `folders`, checked where they hold it.
+pub fn picker(action: FolderPickerAction, folders: Vec<api::FavFolder>) -> FolderPicker {
+    let checked = folders.iter().map(|f| f.fav_state == 1).collect();
+    FolderPicker {
+        action,
+        aid: 170001,
+        mid: 2,
+        folders,
+        checked,
+        list_state: ListState::default(),
+    }
+}
+
+/// An original video with 100 views, 10 likes, 5 coins, 3 favorites and 1 share.
+pub fn video_info(aid: u64, bvid: &str) -> api::VideoInfo {
+    serde_json::from_value(serde_json::json!({
+        "aid": aid,
+        "bvid": bvid,
+        "title": bvid,
+        "copyright": 1,
+        "owner": {"mid": 2, "name": "碧诗"},
+        "stat": {"view": 100, "like": 10, "coin": 5, "favorite": 3, "share": 1},
+    }))
+    .unwrap()
+}
+
+pub fn details(bvid: &str) -> VideoDetails {
+    VideoDetails {
+        info: video_info(170001, bvid),
+        relation: None,
+        related: Vec::new(),
+    }
+}
+
+/// An app showing the details of video `aid`; `relation` is `None` when logged out.
+pub fn detail_app(aid: u64, relation: Option<api::Relation>) -> App {
+    let mut app = App::new();
+    app.video_info = Some(video_info(aid, "BV17x411w7KC"));
+    app.relation = relation;
+    app
+}
@@ -1,18 +1,30 @@
+mod action;
 mod api;
 mod app;
+mod cache;
 mod command;
+mod cover;
+mod danmaku;
+mod effect;
+#[cfg(test)]
+mod fixtures;
+mod recorder;
+mod scheduler;
+mod storage;
+mod task;
 mod ui;
 
-use app::{App, Focusable, InputMode};
+use action::Action;
+use app::{App, LiveChat, Recording};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind},
+    event::{DisableMouseCapture, EnableMouseCapture, Event, EventStream, KeyEventKind},
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode},
 };
+use futures_util::{StreamExt, future::select_all};
 use ratatui::prelude::*;
 use std::{error::Error, io, time::Duration};
-use tokio::sync::mpsc;
-use tui_input::backend::crossterm::EventHandler;
+use task::{AppEvent, Slot};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
@@ -24,6 +36,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
     let mut terminal = Terminal::new(backend)?;
 
     // create app and run it
+    cache::set_offline(std::env::var("BILI_TUI_OFFLINE").is_ok_and(|v| v == "1"));
     let app = App::new();
     let res = run_app(&mut terminal, app).await;
 
@@ -43,191 +56,110 @@ async fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
-    let (tx, mut rx) = mpsc::channel(1);
+async fn run_app<B: Backend + io::Write>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+) -> io::Result<()> {
+    // Land on the recommended feed when logged in, otherwise stay on the start screen.
+    app.tasks.spawn(Some(Slot::View), "Checking login", async {
+        let logged_in = api::current_user_mid().await.is_ok();
+        Ok(AppEvent::LoginChecked { logged_in })
+    });
 
+    let mut events = EventStream::new();
+    let mut dirty = true;
     loop {
-        terminal.draw(|f| ui::ui(f, &mut app))?;
-
-        if let Ok(response) = rx.try_recv() {
-            match response {
-                Ok(results) => {
-                    app.search_results = results;
-                    if !app.search_results.is_empty() {
-                        app.results_list_state.select(Some(0));
-                    }
-                    app.mode = InputMode::ListNav;
-                    app.focused_panel = Focusable::Results;
-                    app.last_error = None;
-                }
-                Err(e) => {
-                    app.last_error = Some(e);
-                }
+        effect::run_pending(&mut app);
+        while dirty {
+            terminal.draw(|f| ui::ui(f, &mut app))?;
+            // A cover repaint clears the screen, so the image goes up with another frame.
+            dirty = app.cover.present(terminal.backend_mut())?;
+            if dirty {
+                terminal.clear()?;
             }
         }
 
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                if key.kind == KeyEventKind::Press {
-                    match app.mode {
-                        InputMode::Normal => match key.code {
-                            KeyCode::Char('q') => {
-                                return Ok(());
-                            }
-                            KeyCode::Char('j') => {
-                                app.focused_panel = app.focused_panel.next();
-                            }
-                            KeyCode::Char('k') => {
-                                app.focused_panel = app.focused_panel.prev();
-                            }
-                            KeyCode::Enter => match app.focused_panel {
-                                Focusable::Search => {
-                                    app.mode = InputMode::Editing;
-                                }
-                                Focusable::Results => {
-                                    app.mode = InputMode::ListNav;
-                                }
-                                Focusable::Command => {
-                                    app.mode = InputMode::Command;
-                                }
-                                Focusable::None => {}
-                            },
-                            KeyCode::Char(':') => {
-                                if app.focused_panel == Focusable::Command {
-                                    app.mode = InputMode::Command;
-                                    app.command_input.reset();
-                                    app.command_input.handle_event(&Event::Key(key));
-                                }
-                            },
-                            KeyCode::Char('/') => {
-                                app.focused_panel = Focusable::Search;
-                                app.mode = InputMode::Editing;
-                            }
-                            _ => {}
-                        },
-                        InputMode::Editing => match key.code {
-                            KeyCode::Enter => {
-                                let query = app.search_input.value().to_string();
-                                let tx = tx.clone();
-                                tokio::spawn(async move {
-                                    let response = match api::search(&query).await {
-                                        Ok(results) => Ok(results),
-                                        Err(e) => Err(e.to_string()),
-                                    };
-                                    let _ = tx.send(response).await;
-                                });
-                                app.mode = InputMode::Normal;
-                            }
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                app.mode = InputMode::Normal;
-                                app.focused_panel = Focusable::None;
-                            }
-                            _ => {
-                                app.search_input.handle_event(&Event::Key(key));
-                            }
-                        },
-                        InputMode::Command => match key.code {
-                            KeyCode::Enter => {
-                                let command_str = app.command_input.value().to_string();
-                                app.command_input.reset();
-                                app.mode = InputMode::Normal;
+        let interval = redraw_interval(&app);
+        let event = tokio::select! {
+            event = events.next() => match event {
+                Some(event) => event?,
+                None => return Ok(()),
+            },
+            result = app.tasks.recv() => {
+                app.update(Action::TaskFinished(result));
+                dirty = true;
+                continue;
+            }
+            () = chat_news(app.live_chat.as_mut()) => {
+                dirty = true;
+                continue;
+            }
+            // Progress alone doesn't redraw; the timer below refreshes running recordings.
+            () = recording_progress(&mut app.recordings) => continue,
+            () = redraw_timer(interval) => {
+                dirty = true;
+                continue;
+            }
+        };
 
-                                match command::parse(&command_str) {
-                                    Ok(cmd) => {
-                                        if let command::Command::Quit = cmd {
-                                            return Ok(());
-                                        }
-                                        match command::execute(cmd, &mut app).await {
-                                            Ok(_) => app.last_error = None,
-                                            Err(e) => app.last_error = Some(e),
-                                        }
-                                    }
-                                    Err(e) => {
-                                        app.last_error = Some(e);
-                                    }
-                                }
-                            }
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                app.mode = InputMode::Normal;
-                                app.focused_panel = Focusable::None;
-                            }
-                            _ => {
-                                app.command_input.handle_event(&Event::Key(key));
-                            }
-                        },
-                        InputMode::Detail => match key.code {
-                            KeyCode::Char('j') => {
-                                app.focused_panel = app.focused_panel.next();
-                            }
-                            KeyCode::Char('k') => {
-                                app.focused_panel = app.focused_panel.prev();
-                            }
-                            KeyCode::Enter => match app.focused_panel {
-                                Focusable::Search => {
-                                    app.mode = InputMode::Editing;
-                                }
-                                Focusable::Command => {
-                                    app.mode = InputMode::Command;
-                                }
-                                _ => {}
-                            },
-                            KeyCode::Char('p') => {
-                                app.play_video();
-                            }
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                app.mode = InputMode::Normal;
-                                app.focused_panel = Focusable::None;
-                                app.video_info = None;
-                            }
-                            _ => {}
-                        },
-                        InputMode::ListNav => match key.code {
-                            KeyCode::Char('j') => {
-                                if !app.search_results.is_empty() {
-                                    let i = match app.results_list_state.selected() {
-                                        Some(i) => {
-                                            if i >= app.search_results.len() - 1 {
-                                                0
-                                            } else {
-                                                i + 1
-                                            }
-                                        }
-                                        None => 0,
-                                    };
-                                    app.results_list_state.select(Some(i));
-                                }
-                            }
-                            KeyCode::Char('k') => {
-                                if !app.search_results.is_empty() {
-                                    let i = match app.results_list_state.selected() {
-                                        Some(i) => {
-                                            if i == 0 {
-                                                app.search_results.len() - 1
-                                            } else {
-                                                i - 1
-                                            }
-                                        }
-                                        None => 0,
-                                    };
-                                    app.results_list_state.select(Some(i));
-                                }
-                            }
-                            KeyCode::Enter => {
-                                app.mode = InputMode::Detail;
-                            }
-                            KeyCode::Char('q') | KeyCode::Esc => {
-                                app.mode = InputMode::Normal;
-                                app.results_list_state.select(None);
-                                app.focused_panel = Focusable::None;
-                            }
-                            _ => {}
-                        },
-                        InputMode::Help => {
-                            app.mode = InputMode::Normal;
-                        }
-                    }
+        match event {
+            Event::Resize(..) => dirty = true,
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                if let Some(action) = action::map_key(&app, key) {
+                    app.update(action);
+                    dirty = true;
+                }
+                if app.should_quit {
+                    return Ok(());
                 }
             }
+            _ => {}
         }
     }
 }
+
+/// Waits for news in the live chat, if one is open.
+async fn chat_news(chat: Option<&mut LiveChat>) {
+    match chat {
+        Some(chat) => chat.receive().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Waits until one of `recordings` reports progress.
+async fn recording_progress(recordings: &mut [Recording]) {
+    if recordings.is_empty() {
+        return std::future::pending().await;
+    }
+    select_all(
+        recordings
+            .iter_mut()
+            .map(|recording| Box::pin(recording.receive())),
+    )
+    .await;
+}
+
+/// How often the screen needs redrawing with nothing else happening: for each spinner frame
+/// while work is in flight, every second while a countdown or recording clock runs, and never
+/// while idle.
+fn redraw_interval(app: &App) -> Option<Duration> {
+    if app.tasks.busy().is_some() {
+        Some(Duration::from_millis(80))
+    } else if scheduler::throttled_for().is_some()
+        || app
+            .recordings
+            .iter()
+            .any(|recording| recording.stopped.is_none())
+    {
+        Some(Duration::from_secs(1))
+    } else {
+        None
+    }
+}
+
+async fn redraw_timer(interval: Option<Duration>) {
+    match interval {
+        Some(interval) => tokio::time::sleep(interval).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,298 @@
+use crate::api;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+/// Start a new file once the current one grows past this many bytes.
+pub const MAX_FILE_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+/// Start a new file once the current one has been recording for this long.
+pub const MAX_FILE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// How long to wait before asking again whether an offline room has gone live.
+const OFFLINE_RETRY: Duration = Duration::from_secs(30);
+/// How long to wait before reconnecting after the stream broke off.
+const RECONNECT_DELAY: Duration = Duration::from_secs(3);
+/// How many HLS segment URIs to remember so a playlist refresh doesn't fetch them twice.
+const SEEN_SEGMENTS: usize = 64;
+
+/// Progress reported by a recording task.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordEvent {
+    /// A new part of the recording was opened at this path.
+    FileStarted(PathBuf),
+    /// This many more bytes were written to the current part.
+    Written(u64),
+    /// The stream is unavailable; the task retries on its own.
+    Waiting(String),
+}
+
+/// Why a part of the recording came to an end.
+enum PartEnd {
+    /// The size or duration limit was reached; continue straight into the next part.
+    Split,
+    /// The receiver was dropped, so nobody wants the recording any more.
+    Stopped,
+}
+
+/// Spawns a task recording `room_id` into `dir` until the receiver is dropped or the task aborted.
+///
+/// The stream is re-resolved for every part, so splitting doubles as a reconnect and each
+/// file starts with its own FLV header and plays on its own.
+pub fn record(
+    room_id: u64,
+    dir: PathBuf,
+) -> (mpsc::Receiver<RecordEvent>, tokio::task::JoinHandle<()>) {
+    let (tx, rx) = mpsc::channel(64);
+    let task = tokio::spawn(async move {
+        loop {
+            let stream = match api::get_live_play_url(room_id, 10000).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    if tx.send(RecordEvent::Waiting(e.to_string())).await.is_err() {
+                        return;
+                    }
+                    tokio::time::sleep(OFFLINE_RETRY).await;
+                    continue;
+                }
+            };
+            match record_part(room_id, &dir, &stream.url, &tx).await {
+                Ok(PartEnd::Split) => {}
+                Ok(PartEnd::Stopped) => return,
+                Err(e) => {
+                    let message = format!("{}; reconnecting", e);
+                    if tx.send(RecordEvent::Waiting(message)).await.is_err() {
+                        return;
+                    }
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+    (rx, task)
+}
+
+/// Path of a new part, named after the room and the local time it started.
+fn part_path(dir: &Path, room_id: u64, extension: &str) -> PathBuf {
+    let started = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    dir.join(format!("{}_{}.{}", room_id, started, extension))
+}
+
+/// Whether `url` points at an HLS playlist rather than a continuous FLV stream.
+fn is_hls(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| url.path().ends_with(".m3u8"))
+}
+
+async fn record_part(
+    room_id: u64,
+    dir: &Path,
+    url: &str,
+    tx: &mpsc::Sender<RecordEvent>,
+) -> Result<PartEnd, String> {
+    let hls = is_hls(url);
+    let path = part_path(dir, room_id, if hls { "ts" } else { "flv" });
+    // Connect before creating the file so an offline room doesn't leave empty parts behind.
+    let response = api::open_live_stream(url)
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut file = tokio::fs::File::create(&path)
+        .await
+        .map_err(|e| format!("failed to create {}: {}", path.display(), e))?;
+    if tx.send(RecordEvent::FileStarted(path)).await.is_err() {
+        return Ok(PartEnd::Stopped);
+    }
+
+    let mut part = Part {
+        file: &mut file,
+        tx,
+        started: Instant::now(),
+        bytes: 0,
+    };
+    let end = if hls {
+        part.copy_hls(url, response).await
+    } else {
+        part.copy_flv(response).await
+    };
+    file.flush().await.map_err(|e| e.to_string())?;
+    end
+}
+
+/// The file currently being written and how much of it is done.
+struct Part<'a> {
+    file: &'a mut tokio::fs::File,
+    tx: &'a mpsc::Sender<RecordEvent>,
+    started: Instant,
+    bytes: u64,
+}
+
+impl Part<'_> {
+    /// Appends `data`, returning how the part ends if it should end now.
+    async fn write(&mut self, data: &[u8]) -> Result<Option<PartEnd>, String> {
+        self.file
+            .write_all(data)
+            .await
+            .map_err(|e| format!("failed to write recording: {}", e))?;
+        self.bytes += data.len() as u64;
+        if self
+            .tx
+            .send(RecordEvent::Written(data.len() as u64))
+            .await
+            .is_err()
+        {
+            return Ok(Some(PartEnd::Stopped));
+        }
+        let full = self.bytes >= MAX_FILE_BYTES || self.started.elapsed() >= MAX_FILE_DURATION;
+        Ok(full.then_some(PartEnd::Split))
+    }
+
+    async fn copy_flv(&mut self, mut response: reqwest::Response) -> Result<PartEnd, String> {
+        while let Some(chunk) = response.chunk().await.map_err(|e| e.to_string())? {
+            if let Some(end) = self.write(&chunk).await? {
+                return Ok(end);
+            }
+        }
+        Err("the stream ended".to_string())
+    }
+
+    /// Polls the playlist and appends every segment not written yet, in order.
+    async fn copy_hls(
+        &mut self,
+        url: &str,
+        response: reqwest::Response,
+    ) -> Result<PartEnd, String> {
+        let base = url::Url::parse(url).map_err(|e| e.to_string())?;
+        let mut seen: VecDeque<String> = VecDeque::new();
+        let mut wrote_init = false;
+        let mut playlist = response.text().await.map_err(|e| e.to_string())?;
+        loop {
+            let parsed = Playlist::parse(&playlist);
+            if let Some(init) = parsed.init.as_deref().filter(|_| !wrote_init) {
+                let data = fetch(&base, init).await?;
+                if let Some(end) = self.write(&data).await? {
+                    return Ok(end);
+                }
+                wrote_init = true;
+            }
+            for segment in parsed.segments {
+                if seen.contains(&segment) {
+                    continue;
+                }
+                let data = fetch(&base, &segment).await?;
+                if seen.len() == SEEN_SEGMENTS {
+                    seen.pop_front();
+                }
+                seen.push_back(segment);
+                if let Some(end) = self.write(&data).await? {
+                    return Ok(end);
+                }
+            }
+            if parsed.ended {
+                return Err("the stream ended".to_string());
+            }
+
+            tokio::time::sleep(parsed.target_duration).await;
+            playlist = api::open_live_stream(url)
+                .await
+                .map_err(|e| e.to_string())?
+                .text()
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+    }
+}
+
+/// Downloads the playlist entry `uri`, which may be relative to the playlist's own URL.
+async fn fetch(base: &url::Url, uri: &str) -> Result<Vec<u8>, String> {
+    let url = base.join(uri).map_err(|e| e.to_string())?;
+    let response = api::open_live_stream(url.as_str())
+        .await
+        .map_err(|e| e.to_string())?;
+    let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+    Ok(bytes.to_vec())
+}
+
+/// The parts of a live HLS media playlist the recorder needs.
+#[derive(Debug, PartialEq)]
+struct Playlist {
+    /// The fMP4 initialization section, which has to precede the segments.
+    init: Option<String>,
+    segments: Vec<String>,
+    target_duration: Duration,
+    ended: bool,
+}
+
+impl Playlist {
+    fn parse(text: &str) -> Self {
+        let mut playlist = Playlist {
+            init: None,
+            segments: Vec::new(),
+            target_duration: Duration::from_secs(2),
+            ended: false,
+        };
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            if let Some(secs) = line.strip_prefix("#EXT-X-TARGETDURATION:") {
+                if let Ok(secs) = secs.parse() {
+                    playlist.target_duration = Duration::from_secs(secs);
+                }
+            } else if let Some(attributes) = line.strip_prefix("#EXT-X-MAP:") {
+                playlist.init = attributes
+                    .split(',')
+                    .find_map(|attribute| attribute.strip_prefix("URI="))
+                    .map(|uri| uri.trim_matches('"').to_string());
+            } else if line == "#EXT-X-ENDLIST" {
+                playlist.ended = true;
+            } else if !line.starts_with('#') {
+                playlist.segments.push(line.to_string());
+            }
+        }
+        playlist
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_a_live_fmp4_playlist() {
+        let playlist = Playlist::parse(
+            "#EXTM3U\n\
+             #EXT-X-VERSION:7\n\
+             #EXT-X-TARGETDURATION:1\n\
+             #EXT-X-MEDIA-SEQUENCE:812\n\
+             #EXT-X-MAP:URI=\"h1717000000.m4s\"\n\
+             #EXTINF:1.00,a|None\n\
+             812.m4s\n\
+             #EXTINF:1.00,a|None\n\
+             813.m4s\n",
+        );
+        assert_eq!(
+            playlist,
+            Playlist {
+                init: Some("h1717000000.m4s".to_string()),
+                segments: vec!["812.m4s".to_string(), "813.m4s".to_string()],
+                target_duration: Duration::from_secs(1),
+                ended: false,
+            }
+        );
+    }
+
+    #[test]
+    fn notices_the_end_of_a_stream() {
+        let playlist = Playlist::parse("#EXTM3U\n#EXTINF:2.0,\n1.ts\n#EXT-X-ENDLIST\n");
+        assert!(playlist.ended);
+        assert_eq!(playlist.init, None);
+        assert_eq!(playlist.target_duration, Duration::from_secs(2));
+    }
+
+    #[test]
+    fn tells_hls_from_flv() {
+        assert!(is_hls(
+            "https://cn-gd.bilivideo.com/live-bvc/123/live_1_2.m3u8?expires=1"
+        ));
+        assert!(!is_hls(
+            "https://cn-gd.bilivideo.com/live-bvc/123/live_1_2.flv?expires=1"
+        ));
+    }
+}
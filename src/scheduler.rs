@@ -0,0 +1,351 @@
+use reqwest::{RequestBuilder, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::hash::BuildHasher;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+type SendResult = Result<String, Box<dyn std::error::Error + Send + Sync>>;
+
+/// The shortest gap between two requests to a host; unlisted hosts use `DEFAULT_INTERVAL`.
+const HOST_INTERVALS: [(&str, Duration); 2] = [
+    ("api.bilibili.com", Duration::from_millis(300)),
+    ("api.live.bilibili.com", Duration::from_millis(200)),
+];
+const DEFAULT_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How many times a failed GET is tried again before giving up.
+const MAX_RETRIES: u32 = 3;
+const BACKOFF_BASE: Duration = Duration::from_secs(1);
+const BACKOFF_MAX: Duration = Duration::from_secs(30);
+
+/// After this many risk-control rejections in a row, requests to the host are refused outright...
+const BREAKER_THRESHOLD: u32 = 3;
+/// ...for this long, before a single request is let through to probe whether it is over. The
+/// others stay refused until that probe is answered.
+const BREAKER_COOL_DOWN: Duration = Duration::from_secs(60);
+
+/// API codes meaning the request was refused for coming too fast: -412 is risk control, -799
+/// "请求过于频繁".
+const THROTTLED_CODES: [i64; 2] = [-412, -799];
+
+/// Rate limit and circuit breaker state of each host.
+static HOSTS: Mutex<BTreeMap<String, Host>> = Mutex::new(BTreeMap::new());
+/// When the request backing off the longest will be tried again; in the past once it has been.
+static RETRY_AT: Mutex<Option<Instant>> = Mutex::new(None);
+
+#[derive(Default)]
+struct Host {
+    /// The earliest time the next request may go out.
+    next_slot: Option<Instant>,
+    /// Throttled responses received in a row.
+    rejections: u32,
+    /// Requests are refused until then while the circuit is open.
+    open_until: Option<Instant>,
+    /// Whether a request is out probing the host after the cool-down.
+    probing: bool,
+}
+
+/// Whether a request may go out to a host.
+#[derive(Debug, PartialEq)]
+enum Admission {
+    /// The circuit is closed.
+    Admit,
+    /// The cool-down is over, and this request finds out whether the host accepts requests again.
+    Probe,
+    /// The circuit is open for this much longer.
+    Refuse(Duration),
+    /// Another request is probing the host.
+    AwaitProbe,
+}
+
+impl Host {
+    fn admit(&mut self, now: Instant) -> Admission {
+        let Some(open_until) = self.open_until else {
+            return Admission::Admit;
+        };
+        if now < open_until {
+            Admission::Refuse(open_until - now)
+        } else if self.probing {
+            Admission::AwaitProbe
+        } else {
+            self.probing = true;
+            Admission::Probe
+        }
+    }
+
+    /// Counts a throttled response towards opening the circuit, or closes it on success. A
+    /// rejected probe opens it again straight away, as the rejections before it still count.
+    fn record(&mut self, throttled: bool, now: Instant) {
+        if !throttled {
+            self.rejections = 0;
+            self.open_until = None;
+            return;
+        }
+        self.rejections += 1;
+        if self.rejections >= BREAKER_THRESHOLD {
+            self.open_until = Some(now + BREAKER_COOL_DOWN);
+        }
+    }
+
+    /// How much longer requests are refused, if the circuit is open.
+    fn open_for(&self, now: Instant) -> Option<Duration> {
+        let wait = self.open_until?.saturating_duration_since(now);
+        (!wait.is_zero()).then_some(wait)
+    }
+}
+
+/// Ends `host`'s probe when dropped, however the probing request turns out, even if cancelled.
+struct ProbeGuard<'a>(&'a str);
+
+impl Drop for ProbeGuard<'_> {
+    fn drop(&mut self) {
+        if let Some(state) = HOSTS.lock().unwrap().get_mut(self.0) {
+            state.probing = false;
+        }
+    }
+}
+
+/// Why an attempt failed, and whether trying again may help.
+enum Failure {
+    Throttled,
+    Transient(String),
+    Fatal(Box<dyn std::error::Error + Send + Sync>),
+}
+
+#[derive(Deserialize)]
+struct Envelope {
+    code: i64,
+}
+
+/// Sends the request `build` makes to `url`, waiting for the host's rate limit. With `retry`,
+/// throttling and transient failures are retried with exponential backoff; only idempotent
+/// requests should ask for that.
+pub async fn send(url: &str, retry: bool, build: impl Fn() -> RequestBuilder) -> SendResult {
+    let host = url::Url::parse(url)?
+        .host_str()
+        .unwrap_or_default()
+        .to_string();
+    let mut attempt = 0;
+    loop {
+        let _probe = match admit(&host) {
+            Admission::Admit => None,
+            Admission::Probe => Some(ProbeGuard(&host)),
+            Admission::Refuse(wait) => return Err(throttled_message(wait).into()),
+            Admission::AwaitProbe => {
+                return Err("Throttled by Bilibili; checking whether requests may resume".into());
+            }
+        };
+        wait_turn(&host).await;
+        let failure = match attempt_once(build()).await {
+            Ok(body) => {
+                record(&host, false);
+                return Ok(body);
+            }
+            Err(failure) => failure,
+        };
+        let message = match failure {
+            Failure::Fatal(e) => return Err(e),
+            Failure::Throttled => {
+                record(&host, true);
+                if let Some(wait) = circuit_open_for(&host) {
+                    return Err(throttled_message(wait).into());
+                }
+                "Bilibili's risk control rejected the request; try again later".to_string()
+            }
+            Failure::Transient(message) => message,
+        };
+        if !retry || attempt == MAX_RETRIES {
+            return Err(message.into());
+        }
+
+        let delay = backoff(attempt, jitter());
+        let retry_at = Instant::now() + delay;
+        // Several requests may be backing off at once; report the one that waits longest.
+        {
+            let mut latest = RETRY_AT.lock().unwrap();
+            *latest = (*latest).max(Some(retry_at));
+        }
+        tokio::time::sleep_until(retry_at.into()).await;
+        attempt += 1;
+    }
+}
+
+async fn attempt_once(request: RequestBuilder) -> Result<String, Failure> {
+    let response = match request.send().await {
+        Ok(response) => response,
+        Err(e) if e.is_connect() || e.is_timeout() => {
+            return Err(Failure::Transient(e.to_string()));
+        }
+        Err(e) => return Err(Failure::Fatal(e.into())),
+    };
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| Failure::Transient(e.to_string()))?;
+    if status == StatusCode::PRECONDITION_FAILED || status == StatusCode::TOO_MANY_REQUESTS {
+        return Err(Failure::Throttled);
+    }
+    if status.is_server_error() {
+        return Err(Failure::Transient(format!("server error {}", status)));
+    }
+    let code = serde_json::from_str::<Envelope>(&body).map_or(0, |envelope| envelope.code);
+    if THROTTLED_CODES.contains(&code) {
+        return Err(Failure::Throttled);
+    }
+    Ok(body)
+}
+
+/// Waits until `host` may be sent another request and reserves the slot after it.
+async fn wait_turn(host: &str) {
+    let interval = HOST_INTERVALS
+        .iter()
+        .find(|(name, _)| *name == host)
+        .map_or(DEFAULT_INTERVAL, |(_, interval)| *interval);
+    let slot = {
+        let mut hosts = HOSTS.lock().unwrap();
+        let state = hosts.entry(host.to_string()).or_default();
+        let now = Instant::now();
+        let slot = state.next_slot.map_or(now, |next| next.max(now));
+        state.next_slot = Some(slot + interval);
+        slot
+    };
+    tokio::time::sleep_until(slot.into()).await;
+}
+
+/// Whether a request may go out to `host` now; see [`Host::admit`].
+fn admit(host: &str) -> Admission {
+    let mut hosts = HOSTS.lock().unwrap();
+    hosts
+        .entry(host.to_string())
+        .or_default()
+        .admit(Instant::now())
+}
+
+/// Counts a throttled response towards opening `host`'s circuit, or closes it on success.
+fn record(host: &str, throttled: bool) {
+    let mut hosts = HOSTS.lock().unwrap();
+    hosts
+        .entry(host.to_string())
+        .or_default()
+        .record(throttled, Instant::now());
+}
+
+/// How much longer requests to `host` are refused, if its circuit is open.
+fn circuit_open_for(host: &str) -> Option<Duration> {
+    HOSTS.lock().unwrap().get(host)?.open_for(Instant::now())
+}
+
+fn throttled_message(wait: Duration) -> String {
+    format!(
+        "Throttled by Bilibili; requests resume in {}s",
+        wait.as_secs() + 1
+    )
+}
+
+/// The delay before retry number `attempt` (from 0): doubling from `BACKOFF_BASE` up to
+/// `BACKOFF_MAX`, then scaled into its upper half by `jitter` in `0.0..1.0` so that retries
+/// from several requests spread out.
+fn backoff(attempt: u32, jitter: f64) -> Duration {
+    let exponential = BACKOFF_BASE
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(BACKOFF_MAX);
+    exponential.mul_f64(0.5 + 0.5 * jitter)
+}
+
+/// A random number in `0.0..1.0`, good enough to spread retries.
+fn jitter() -> f64 {
+    let random = std::collections::hash_map::RandomState::new().hash_one(Instant::now());
+    (random % 1000) as f64 / 1000.0
+}
+
+/// How long until requests resume, while one is backing off or a circuit is open.
+pub fn throttled_for() -> Option<Duration> {
+    let now = Instant::now();
+    let retry_at = *RETRY_AT.lock().unwrap();
+    let open_until = HOSTS
+        .lock()
+        .unwrap()
+        .values()
+        .filter_map(|host| host.open_until)
+        .max();
+    let until = retry_at.max(open_until)?;
+    let wait = until.saturating_duration_since(now);
+    (!wait.is_zero()).then_some(wait)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially_up_to_a_cap() {
+        assert_eq!(backoff(0, 1.0), Duration::from_secs(1));
+        assert_eq!(backoff(2, 1.0), Duration::from_secs(4));
+        assert_eq!(backoff(10, 1.0), BACKOFF_MAX);
+        assert_eq!(backoff(2, 0.0), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn opens_the_circuit_after_repeated_rejections() {
+        let mut host = Host::default();
+        let now = Instant::now();
+        for _ in 1..BREAKER_THRESHOLD {
+            host.record(true, now);
+        }
+        assert_eq!(host.admit(now), Admission::Admit);
+        host.record(true, now);
+        assert_eq!(host.open_for(now), Some(BREAKER_COOL_DOWN));
+        assert_eq!(host.admit(now), Admission::Refuse(BREAKER_COOL_DOWN));
+    }
+
+    #[test]
+    fn lets_a_single_probe_through_after_the_cool_down() {
+        let mut host = Host::default();
+        let now = Instant::now();
+        for _ in 0..BREAKER_THRESHOLD {
+            host.record(true, now);
+        }
+
+        let later = now + BREAKER_COOL_DOWN;
+        assert_eq!(host.open_for(later), None);
+        assert_eq!(host.admit(later), Admission::Probe);
+        assert_eq!(host.admit(later), Admission::AwaitProbe);
+
+        // A rejected probe opens the circuit again.
+        host.record(true, later);
+        host.probing = false;
+        assert_eq!(host.admit(later), Admission::Refuse(BREAKER_COOL_DOWN));
+
+        // A probe that fails otherwise leaves the next request to probe.
+        let later = later + BREAKER_COOL_DOWN;
+        assert_eq!(host.admit(later), Admission::Probe);
+        host.probing = false;
+        assert_eq!(host.admit(later), Admission::Probe);
+
+        // A probe that goes through closes the circuit.
+        host.record(false, later);
+        host.probing = false;
+        assert_eq!(host.admit(later), Admission::Admit);
+        assert_eq!(host.admit(later), Admission::Admit);
+    }
+
+    #[test]
+    fn ends_the_probe_when_the_request_is_dropped() {
+        let host = "probe.test";
+        HOSTS.lock().unwrap().insert(
+            host.to_string(),
+            Host {
+                rejections: BREAKER_THRESHOLD,
+                open_until: Some(Instant::now()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(admit(host), Admission::Probe);
+        let probe = ProbeGuard(host);
+        assert_eq!(admit(host), Admission::AwaitProbe);
+        drop(probe);
+        assert_eq!(admit(host), Admission::Probe);
+    }
+}
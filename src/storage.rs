@@ -0,0 +1,42 @@
+use std::{fs, io, path::PathBuf};
+
+/// The directory bili-tui keeps its persistent state in, e.g. `~/.local/share/bili-tui`.
+pub fn data_dir() -> io::Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?
+        .join("bili-tui");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// Publish time of the newest feed item seen on the previous visit, or 0 if never visited.
+pub fn feed_last_seen() -> i64 {
+    data_dir()
+        .and_then(|dir| fs::read_to_string(dir.join("feed_last_seen")))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or(0)
+}
+
+pub fn set_feed_last_seen(timestamp: i64) -> io::Result<()> {
+    fs::write(data_dir()?.join("feed_last_seen"), timestamp.to_string())
+}
+
+/// Where live recordings are written: the user's video directory, or the data directory without one.
+pub fn recordings_dir() -> io::Result<PathBuf> {
+    let dir = match dirs::video_dir() {
+        Some(videos) => videos.join("bili-tui"),
+        None => data_dir()?.join("recordings"),
+    };
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+/// The directory for data that can be thrown away and fetched again, e.g. `~/.cache/bili-tui`.
+pub fn cache_dir() -> io::Result<PathBuf> {
+    let dir = dirs::cache_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no cache directory"))?
+        .join("bili-tui");
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
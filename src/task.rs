@@ -0,0 +1,281 @@
+use crate::api;
+use crate::app::{FolderPicker, QualityPicker, ResultsSource, UploaderView, VideoDetails};
+use futures_util::future::BoxFuture;
+use image::RgbImage;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// What a background task reports back to the main loop once its network work is done.
+/// [`crate::command::apply`] brings each one into the app.
+pub enum AppEvent {
+    /// Whether a user is logged in, checked at startup.
+    LoginChecked {
+        logged_in: bool,
+    },
+    /// A new list for the results panel. With `focus`, the list is also brought on screen.
+    Results {
+        videos: Vec<api::VideoResult>,
+        source: ResultsSource,
+        focus: bool,
+    },
+    /// The next batch of the recommended feed, loaded with `fresh_idx`.
+    Recommendations {
+        fresh_idx: u32,
+        batch: Vec<(api::VideoResult, api::DislikeTarget)>,
+    },
+    /// A recommendation was reported as unwanted and can leave the list.
+    Disliked(String),
+    Details {
+        bvid: String,
+        result: Result<Box<VideoDetails>, String>,
+    },
+    /// The cover at `url`, loaded separately so the rest of the detail view need not wait.
+    Cover {
+        url: String,
+        result: Result<Arc<RgbImage>, String>,
+    },
+    Uploader(UploaderView),
+    UploaderPage {
+        mid: u64,
+        page: u32,
+        order: api::SpaceOrder,
+        videos: api::SpaceVideos,
+    },
+    FavoritesOpened(Vec<api::FavFolder>),
+    /// The folder list after a change, for an open favorites view.
+    FavFoldersChanged {
+        folders: Vec<api::FavFolder>,
+        message: Option<String>,
+    },
+    FolderPicker(FolderPicker),
+    /// The folder picker's choice was saved; `favorited` is the video's new state, if it changed.
+    FavoritesSaved {
+        aid: u64,
+        favorited: Option<bool>,
+        message: String,
+    },
+    /// A page of watch history; `open` switches to the history view.
+    HistoryPage {
+        page: api::HistoryPage,
+        open: bool,
+    },
+    HistoryDeleted(u64),
+    WatchLaterRemoved(u64),
+    /// A page of the following feed. Opening the feed also brings when it was last looked at,
+    /// and switches to the feed view.
+    FeedPage {
+        page: api::FeedPage,
+        last_seen: Option<i64>,
+    },
+    LiveOpened {
+        tabs: Vec<crate::app::LiveTab>,
+        tab: usize,
+        rooms: Vec<api::LiveRoom>,
+    },
+    LiveRooms {
+        tab: usize,
+        rooms: Vec<api::LiveRoom>,
+    },
+    QualityPicker(QualityPicker),
+    PlayLive {
+        room_id: u64,
+        url: String,
+    },
+    RecordRoom(api::LiveRoom),
+    Season {
+        season: api::Season,
+        episode: Option<u64>,
+    },
+    PlayEpisode(api::Episode),
+    /// A write went through; the message says what happened.
+    Done(String),
+    /// A like or coin failed; the saved counters go back.
+    InteractionFailed {
+        snapshot: (u64, api::Stat, api::Relation),
+        error: String,
+    },
+    Tripled {
+        snapshot: (u64, api::Stat, api::Relation),
+        result: api::TripleResult,
+    },
+}
+
+/// Groups of tasks of which only the newest matters, so starting one cancels the one before.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Slot {
+    /// Loads what the main panel shows: searches, lists, pages and views.
+    View,
+    /// Loads a popup such as the folder or quality picker.
+    Popup,
+}
+
+struct Running {
+    id: u64,
+    slot: Option<Slot>,
+    /// What the spinner says while the task runs; quiet tasks have none.
+    label: Option<&'static str>,
+    started: Instant,
+    /// Unset until the main loop starts the task.
+    handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+/// The background tasks in flight and the channel they report on.
+///
+/// Spawning only queues the work; the main loop starts it with [`Tasks::start_queued`], so
+/// updating the app never touches the runtime.
+pub struct Tasks {
+    running: Vec<Running>,
+    queued: Vec<(u64, BoxFuture<'static, Result<AppEvent, String>>)>,
+    next_id: u64,
+    tx: mpsc::UnboundedSender<(u64, Result<AppEvent, String>)>,
+    rx: mpsc::UnboundedReceiver<(u64, Result<AppEvent, String>)>,
+}
+
+impl Tasks {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        Self {
+            running: Vec::new(),
+            queued: Vec::new(),
+            next_id: 0,
+            tx,
+            rx,
+        }
+    }
+
+    /// Queues `work` to run in the background, cancelling the task running in `slot` first. Tasks
+    /// without a slot, such as writes, are never cancelled.
+    pub fn spawn<F>(&mut self, slot: Option<Slot>, label: &'static str, work: F)
+    where
+        F: Future<Output = Result<AppEvent, String>> + Send + 'static,
+    {
+        if let Some(slot) = slot {
+            self.cancel(slot);
+        }
+        self.start(slot, Some(label), work);
+    }
+
+    /// Queues `work` to run in the background without showing the spinner, e.g. for prefetching.
+    pub fn spawn_quiet<F>(&mut self, work: F)
+    where
+        F: Future<Output = Result<AppEvent, String>> + Send + 'static,
+    {
+        self.start(None, None, work);
+    }
+
+    fn start<F>(&mut self, slot: Option<Slot>, label: Option<&'static str>, work: F)
+    where
+        F: Future<Output = Result<AppEvent, String>> + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.queued.push((id, Box::pin(work)));
+        self.running.push(Running {
+            id,
+            slot,
+            label,
+            started: Instant::now(),
+            handle: None,
+        });
+    }
+
+    /// Starts the work queued since the last call, skipping tasks cancelled in the meantime.
+    pub fn start_queued(&mut self) {
+        for (id, work) in std::mem::take(&mut self.queued) {
+            let Some(task) = self.running.iter_mut().find(|task| task.id == id) else {
+                continue;
+            };
+            let tx = self.tx.clone();
+            task.handle = Some(tokio::spawn(async move {
+                let _ = tx.send((id, work.await));
+            }));
+        }
+    }
+
+    /// The labels of the queued tasks, oldest first, for checking what an update asked for.
+    #[cfg(test)]
+    pub fn queued(&self) -> Vec<Option<&'static str>> {
+        self.queued
+            .iter()
+            .filter_map(|(id, _)| self.running.iter().find(|task| task.id == *id))
+            .map(|task| task.label)
+            .collect()
+    }
+
+    /// Aborts the task running in `slot`, if any; whatever it finds is dropped.
+    pub fn cancel(&mut self, slot: Slot) {
+        self.running.retain(|task| {
+            let superseded = task.slot == Some(slot);
+            if superseded && let Some(handle) = &task.handle {
+                handle.abort();
+            }
+            !superseded
+        });
+    }
+
+    /// Waits for the next task to finish and returns its result, skipping cancelled ones.
+    pub async fn recv(&mut self) -> Result<AppEvent, String> {
+        loop {
+            // `self.tx` keeps the channel open, so there is always another result to wait for.
+            let Some((id, result)) = self.rx.recv().await else {
+                return std::future::pending().await;
+            };
+            // A cancelled task may have reported just before it was aborted.
+            if let Some(i) = self.running.iter().position(|task| task.id == id) {
+                self.running.remove(i);
+                return result;
+            }
+        }
+    }
+
+    /// What the newest task that shows the spinner is doing, and since when.
+    pub fn busy(&self) -> Option<(&'static str, Instant)> {
+        self.running
+            .iter()
+            .rev()
+            .find_map(|task| Some((task.label?, task.started)))
+    }
+}
+
+impl Drop for Tasks {
+    fn drop(&mut self) {
+        for handle in self.running.iter().filter_map(|task| task.handle.as_ref()) {
+            handle.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_newer_task_in_the_same_slot_cancels_the_older_one() {
+        let mut tasks = Tasks::new();
+        tasks.spawn(Some(Slot::View), "Loading", async {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            Ok(AppEvent::Disliked("old".to_string()))
+        });
+        tasks.spawn(Some(Slot::View), "Loading", async {
+            Ok(AppEvent::Disliked("new".to_string()))
+        });
+        tasks.spawn(None, "Saving", async { Err("failed".to_string()) });
+        assert_eq!(tasks.queued(), [Some("Loading"), Some("Saving")]);
+        tasks.start_queued();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            received.push(match tasks.recv().await {
+                Ok(AppEvent::Disliked(which)) => which,
+                Ok(_) => unreachable!(),
+                Err(e) => e,
+            });
+        }
+        received.sort();
+        assert_eq!(received, ["failed", "new"]);
+        assert!(tasks.busy().is_none());
+    }
+}
@@ -1,9 +1,19 @@
-use crate::app::{App, Focusable, InputMode};
+use crate::api;
+use crate::app::{
+    self, App, FavoritesView, FeedView, Focusable, FolderPicker, FolderPickerAction, HistoryView,
+    InputMode, LiveChat, LiveView, QualityPicker, Recording, ResultsSource, SeasonView,
+    UploaderView,
+};
+use crate::cache;
+use crate::cover;
+use crate::danmaku::ChatEvent;
+use crate::scheduler;
+use chrono::{Local, TimeZone};
 use ratatui::{
     prelude::*,
-    widgets::{Block, Borders, List, ListItem, Paragraph},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs},
 };
-use textwrap;
+use std::time::Instant;
 
 pub fn ui(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
@@ -15,9 +25,16 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         ])
         .split(f.size());
 
+    let mut search_title = match &app.results_source {
+        ResultsSource::Favorites(fav) => format!("Search in {}", fav.folder.title),
+        _ => "Search".to_string(),
+    };
+    if cache::is_offline() {
+        search_title.push_str(" [offline]");
+    }
     let search_bar = Paragraph::new(app.search_input.value()).block(
         Block::default()
-            .title("Search")
+            .title(search_title)
             .borders(Borders::ALL)
             .border_style(if app.focused_panel == Focusable::Search {
                 Style::default().fg(Color::Green)
@@ -36,37 +53,37 @@ pub fn ui(f: &mut Frame, app: &mut App) {
 
     match app.mode {
         InputMode::Detail => {
-            let detail_text = if let Some(info) = &app.video_info {
-                vec![
-                    Line::from(vec!["Title: ".bold(), Span::raw(info.title.clone())]),
-                    Line::from(vec!["Author: ".bold(), Span::raw(info.owner.name.clone())]),
-                    Line::from(vec!["Plays: ".bold(), Span::raw(info.stat.view.to_string())]),
-                    Line::from(vec!["Likes: ".bold(), Span::raw(info.stat.like.to_string())]),
-                    Line::from(""),
-                    Line::from(Span::raw(info.desc.clone())),
-                    Line::from(""),
-                    Line::from("[P]lay with mpv".bold()),
-                ]
-            } else if let Some(selected) = app.results_list_state.selected() {
-                if let Some(video) = app.search_results.get(selected) {
-                    let text = vec![
-                        Line::from(vec![
-                            "Title: ".bold(),
-                            Span::raw(video.title.clone()),
-                        ]),
-                        Line::from(vec![
-                            "Plays: ".bold(),
-                            Span::raw(video.play.to_string().trim_matches('"').to_string()),
-                        ]),
-                        Line::from(vec![
-                            "Likes: ".bold(),
-                            Span::raw(video.like.to_string()),
-                        ]),
-                        Line::from(""),
-                        Line::from(Span::raw(video.description.clone())),
-                        Line::from(""),
-                        Line::from("[P]lay with mpv".bold()),
+            // Shown until full details are loaded: what the results list already knows.
+            let detail_text = if let Some(selected) = app.results_list_state.selected() {
+                if let Some(video) = app.search_results.get(selected).filter(|video| {
+                    app.detail_loading
+                        .as_ref()
+                        .is_none_or(|bvid| *bvid == video.bvid)
+                }) {
+                    let mut facts = vec![api::format_duration(video.duration.as_secs())];
+                    if let Some(pubdate) = video.pubdate {
+                        facts.push(relative_time(pubdate.timestamp(), Local::now().timestamp()));
+                    }
+                    let mut text = vec![
+                        Line::from(
+                            [
+                                vec!["Title: ".bold()],
+                                highlighted_spans(&video.title, 0..video.title.text.len()),
+                            ]
+                            .concat(),
+                        ),
+                        Line::from(facts.join(" · ").dark_gray()),
+                        Line::from(vec!["Plays: ".bold(), Span::raw(format_play(video.play))]),
+                        Line::from(vec!["Likes: ".bold(), Span::raw(format_count(video.like))]),
                     ];
+                    if !video.tags.is_empty() {
+                        let tags: Vec<String> =
+                            video.tags.iter().map(|tag| format!("#{}", tag)).collect();
+                        text.push(Line::from(tags.join(" ").cyan()));
+                    }
+                    text.push(Line::from(""));
+                    text.extend(highlighted_lines(&video.description));
+                    text.extend([Line::from(""), Line::from("[P]lay with mpv".bold())]);
                     text
                 } else {
                     vec![]
@@ -74,25 +91,168 @@ pub fn ui(f: &mut Frame, app: &mut App) {
             } else {
                 vec![]
             };
-            let info_panel = Paragraph::new(detail_text)
-                .wrap(ratatui::widgets::Wrap { trim: true })
+            let detail_area = if app.related.is_empty() {
+                chunks[1]
+            } else {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
+                    .split(chunks[1]);
+
+                let related: Vec<ListItem> = app
+                    .related
+                    .iter()
+                    .map(|video| {
+                        video_list_item(
+                            &video.title,
+                            &video.owner.name,
+                            &format_count(video.stat.view),
+                            columns[1].width,
+                        )
+                    })
+                    .collect();
+                let related_list = List::new(related)
+                    .block(
+                        Block::default()
+                            .borders(Borders::ALL)
+                            .title("Related")
+                            .border_style(if app.focused_panel == Focusable::Results {
+                                Style::default().fg(Color::Green)
+                            } else {
+                                Style::default()
+                            }),
+                    )
+                    .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                    .highlight_symbol(">> ");
+                f.render_stateful_widget(related_list, columns[1], &mut app.related_list_state);
+
+                columns[0]
+            };
+            let title = if app.detail_loading.is_some() {
+                "Video Details (loading…)"
+            } else {
+                "Video Details"
+            };
+            let block = Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(if app.focused_panel == Focusable::Results {
+                    Style::default().fg(Color::Green)
+                } else {
+                    Style::default()
+                });
+            let inner = block.inner(detail_area);
+            f.render_widget(block, detail_area);
+            if app.video_info.is_some() {
+                render_video_info(f, app, inner);
+            } else {
+                let info_panel =
+                    Paragraph::new(detail_text).wrap(ratatui::widgets::Wrap { trim: true });
+                f.render_widget(info_panel, inner);
+            }
+        }
+        InputMode::Uploader => {
+            if let Some(view) = app.uploader.as_mut() {
+                render_uploader(f, view, chunks[1]);
+            }
+        }
+        InputMode::Favorites => {
+            if let Some(view) = app.favorites.as_mut() {
+                render_favorites(f, view, chunks[1]);
+            }
+        }
+        InputMode::History => {
+            if let Some(view) = app.history.as_mut() {
+                render_history(f, view, chunks[1]);
+            }
+        }
+        InputMode::Feed => {
+            if let Some(view) = app.feed.as_mut() {
+                render_feed(f, view, chunks[1]);
+            }
+        }
+        InputMode::Start => {
+            let items: Vec<ListItem> = app::START_MENU
+                .iter()
+                .map(|(label, command)| {
+                    ListItem::new(Line::from(vec![
+                        Span::raw(label.to_string()),
+                        Span::raw(format!("  {}", command)).fg(Color::DarkGray),
+                    ]))
+                })
+                .collect();
+            let menu = List::new(items)
                 .block(
                     Block::default()
-                        .title("Video Details")
                         .borders(Borders::ALL)
-                        .border_style(if app.focused_panel == Focusable::Results {
-                            Style::default().fg(Color::Green)
-                        } else {
-                            Style::default()
-                        }),
-                );
-            f.render_widget(info_panel, chunks[1]);
+                        .title("Start [j/k] move [Enter] open [/] search [:] command")
+                        .border_style(Style::default().fg(Color::Green)),
+                )
+                .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(menu, chunks[1], &mut app.start_list_state);
+        }
+        InputMode::Live => {
+            let (rooms_area, chat_area) = match (&app.live, &app.live_chat) {
+                (Some(_), Some(_)) => {
+                    let columns = Layout::default()
+                        .direction(Direction::Horizontal)
+                        .constraints([Constraint::Percentage(55), Constraint::Percentage(45)])
+                        .split(chunks[1]);
+                    (Some(columns[0]), Some(columns[1]))
+                }
+                (Some(_), None) => (Some(chunks[1]), None),
+                (None, _) => (None, Some(chunks[1])),
+            };
+            if let (Some(view), Some(area)) = (app.live.as_mut(), rooms_area) {
+                render_live(f, view, area);
+            }
+            if let (Some(chat), Some(area)) = (&app.live_chat, chat_area) {
+                render_chat(f, chat, area);
+            }
+        }
+        InputMode::Season => {
+            if let Some(view) = app.season.as_mut() {
+                render_season(f, view, chunks[1]);
+            }
+        }
+        InputMode::Recordings => {
+            render_recordings(
+                f,
+                &app.recordings,
+                &mut app.recordings_list_state,
+                chunks[1],
+            );
         }
         InputMode::Help => {
             let help_text = vec![
                 Line::from("Commands:".bold()),
                 Line::from("  :video <url>       - Play video with mpv"),
                 Line::from("  :video-info <url>  - Show video details"),
+                Line::from("  :up <mid|name>     - Show an uploader's profile and videos"),
+                Line::from("  :fav               - Browse your favorites folders"),
+                Line::from("  :fav-new <title>   - Create a favorites folder"),
+                Line::from("  :fav-rename <title> - Rename the selected favorites folder"),
+                Line::from("  :history [date]    - Show watch history, optionally for one day"),
+                Line::from("  :later             - Show your Watch Later list"),
+                Line::from("  :feed              - Show new videos from uploaders you follow"),
+                Line::from("  :rcmd              - Show your recommended feed"),
+                Line::from("  :popular           - Show popular videos"),
+                Line::from("  :weekly [N]        - Show the weekly must-watch, latest or issue N"),
+                Line::from("  :rank [zone]       - Show the ranking board, e.g. :rank game"),
+                Line::from(
+                    "  :live              - Browse live rooms by area or followed uploaders",
+                ),
+                Line::from("  :room <id>         - Play a live room, choosing the quality"),
+                Line::from("  :record <id>       - Record a live room to disk"),
+                Line::from(
+                    "  :season <id|url>   - Show a bangumi season (ss…/ep…) and its episodes",
+                ),
+                Line::from("  :recordings        - Show recordings in progress"),
+                Line::from("  :tag <name>        - Show videos carrying a tag"),
+                Line::from("  :cache stats|clear - Show or empty the response cache"),
+                Line::from("  :offline [on|off]  - Browse cached data only, or go back online"),
+                Line::from("  :start             - Return to the start screen"),
                 Line::from("  :help              - Show this help message"),
                 Line::from("  :q                 - Quit the application"),
                 Line::from(""),
@@ -100,6 +260,57 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 Line::from("  j/k                - Move focus between panels"),
                 Line::from("  Enter              - Select/Enter panel"),
                 Line::from("  q/Esc              - Exit current mode/panel"),
+                Line::from("  h/l                - Previous/next page, issue or ranking zone"),
+                Line::from("  x/X                - Not interested in a recommended video/uploader"),
+                Line::from(""),
+                Line::from("Video details:".bold()),
+                Line::from(
+                    "  j/k                - Move focus, or through related videos once focused",
+                ),
+                Line::from("  Enter              - Open the selected related video"),
+                Line::from("  Tab/Shift-Tab      - Move focus between panels"),
+                Line::from("  u                  - Open the uploader's space"),
+                Line::from("  p                  - Play with mpv"),
+                Line::from("  l                  - Like/unlike"),
+                Line::from("  c/C                - Give 1/2 coins"),
+                Line::from("  t                  - Like, coin and favorite at once"),
+                Line::from("  [/]                - Select the previous/next tag"),
+                Line::from("  #                  - Show videos carrying the selected tag"),
+                Line::from(""),
+                Line::from("Uploader:".bold()),
+                Line::from("  j/k                - Move through uploads"),
+                Line::from("  h/l                - Previous/next page"),
+                Line::from("  s                  - Cycle sort order"),
+                Line::from("  Enter/p            - Show details/play"),
+                Line::from(""),
+                Line::from("Favorites:".bold()),
+                Line::from("  Enter              - Open the selected folder"),
+                Line::from("  h/l                - Previous/next page of a folder"),
+                Line::from("  /                  - Search within the open folder"),
+                Line::from("  n/r                - Create/rename a folder"),
+                Line::from("  f                  - Add/remove the selected video in folders"),
+                Line::from("  m/c                - Move/copy a video to another folder"),
+                Line::from(""),
+                Line::from("Watch Later:".bold()),
+                Line::from("  w                  - Add the selected video to Watch Later"),
+                Line::from("  d                  - Remove the selected video"),
+                Line::from("  C                  - Clear watched videos"),
+                Line::from("  P                  - Play the whole list in order"),
+                Line::from(""),
+                Line::from("History:".bold()),
+                Line::from("  p                  - Resume playback where you left off"),
+                Line::from("  d                  - Delete the selected entry"),
+                Line::from("  l                  - Load older entries"),
+                Line::from(""),
+                Line::from("Live:".bold()),
+                Line::from("  h/l                - Switch between Following and area tabs"),
+                Line::from("  Enter/p            - Pick a quality, play and follow the chat"),
+                Line::from("  c                  - Leave the room's chat"),
+                Line::from("  R                  - Record the selected room"),
+                Line::from(""),
+                Line::from("Recordings:".bold()),
+                Line::from("  s                  - Stop the selected recording"),
+                Line::from("  d                  - Dismiss a stopped recording"),
             ];
             let help_panel = Paragraph::new(help_text)
                 .block(Block::default().title("Help").borders(Borders::ALL));
@@ -110,27 +321,12 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 .search_results
                 .iter()
                 .map(|video| {
-                    let text_width = chunks[1].width.saturating_sub(6) as usize;
-                    let options = textwrap::Options::new(text_width)
-                        .initial_indent("")
-                        .subsequent_indent("  ");
-
-                    let title_wrapped = textwrap::wrap(&video.title, options);
-
-                    let mut lines: Vec<Line> = title_wrapped
-                        .iter()
-                        .map(|s| Line::from(s.to_string()))
-                        .collect();
-
-                    let meta_info = format!(
-                        "{} (▶ {})",
-                        video.author,
-                        video.play.to_string().trim_matches('"')
-                    );
-                    lines.push(Line::from(meta_info.italic().fg(Color::DarkGray)));
-                    lines.push(Line::from("")); // Add blank line for spacing
-
-                    ListItem::new(lines)
+                    highlighted_list_item(
+                        &video.title,
+                        &video.author,
+                        &format_play(video.play),
+                        chunks[1].width,
+                    )
                 })
                 .collect();
 
@@ -138,7 +334,7 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
-                        .title("Results")
+                        .title(app.results_title())
                         .border_style(if app.focused_panel == Focusable::Results {
                             Style::default().fg(Color::Green)
                         } else {
@@ -148,11 +344,38 @@ pub fn ui(f: &mut Frame, app: &mut App) {
                 .highlight_style(Style::default().add_modifier(Modifier::BOLD))
                 .highlight_symbol(">> ");
 
-            f.render_stateful_widget(results_list, chunks[1], &mut app.results_list_state);
+            let mut results_area = chunks[1];
+            if let ResultsSource::Ranking { zone } = app.results_source {
+                let rows = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Length(1), Constraint::Min(0)])
+                    .split(chunks[1]);
+                let tabs = Tabs::new(api::RANK_ZONES.iter().map(|z| z.label))
+                    .select(zone)
+                    .highlight_style(
+                        Style::default()
+                            .fg(Color::Green)
+                            .add_modifier(Modifier::BOLD),
+                    );
+                f.render_widget(tabs, rows[0]);
+                results_area = rows[1];
+            }
+
+            f.render_stateful_widget(results_list, results_area, &mut app.results_list_state);
         }
     }
 
-    if let Some(error) = &app.last_error {
+    let throttled = scheduler::throttled_for().filter(|_| !app.is_commanding());
+    if let Some(wait) = throttled {
+        let status = Paragraph::new(format!("Throttled, retrying in {}s", wait.as_secs() + 1))
+            .block(
+                Block::default()
+                    .title("Status")
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow)),
+            );
+        f.render_widget(status, chunks[2]);
+    } else if let Some(error) = &app.last_error {
         let command_line = Paragraph::new(error.as_str()).block(
             Block::default()
                 .title("Error")
@@ -174,10 +397,884 @@ pub fn ui(f: &mut Frame, app: &mut App) {
         f.render_widget(command_line, chunks[2]);
     }
 
+    if let Some((label, started)) = app.tasks.busy() {
+        render_spinner(f, label, started, chunks[2]);
+    }
+
     if app.is_commanding() {
         f.set_cursor(
             chunks[2].x + app.command_input.visual_cursor() as u16 + 1,
             chunks[2].y + 1,
         );
     }
+
+    if let Some(picker) = app.folder_picker.as_mut() {
+        render_folder_picker(f, picker, centered_rect(60, 60, f.size()));
+    }
+
+    if let Some(picker) = app.quality_picker.as_mut() {
+        render_quality_picker(f, picker, centered_rect(40, 40, f.size()));
+    }
+}
+
+fn render_uploader(f: &mut Frame, view: &mut UploaderView, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(6), Constraint::Min(0)])
+        .split(area);
+
+    let card = &view.profile.card;
+    let profile_text = vec![
+        Line::from(vec![
+            Span::raw(card.name.clone()).bold(),
+            Span::raw(format!("  Lv{}", card.level_info.current_level)),
+        ]),
+        Line::from(vec![
+            "Followers: ".bold(),
+            Span::raw(view.profile.follower.to_string()),
+            "  Following: ".bold(),
+            Span::raw(card.attention.to_string()),
+            "  Videos: ".bold(),
+            Span::raw(view.profile.archive_count.to_string()),
+        ]),
+        Line::from(""),
+        Line::from(Span::raw(card.sign.clone()).italic()),
+    ];
+    let profile_panel = Paragraph::new(profile_text)
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(Block::default().title("Uploader").borders(Borders::ALL));
+    f.render_widget(profile_panel, rows[0]);
+
+    let videos: Vec<ListItem> = view
+        .videos
+        .iter()
+        .map(|video| {
+            video_list_item(
+                &video.title,
+                &video.length,
+                &format_play(video.play),
+                rows[1].width,
+            )
+        })
+        .collect();
+    let title = format!(
+        "Uploads (page {}/{}, {}) [h/l] page [s] sort",
+        view.page,
+        view.page_count(),
+        view.order.label()
+    );
+    let videos_list = List::new(videos)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(videos_list, rows[1], &mut view.list_state);
+}
+
+fn render_favorites(f: &mut Frame, view: &mut FavoritesView, area: Rect) {
+    let folders: Vec<ListItem> = view
+        .folders
+        .iter()
+        .map(|folder| {
+            ListItem::new(Line::from(vec![
+                Span::raw(folder.title.clone()),
+                Span::raw(format!(" ({})", folder.media_count)).fg(Color::DarkGray),
+            ]))
+        })
+        .collect();
+    let folders_list = List::new(folders)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Favorites")
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(folders_list, area, &mut view.list_state);
+}
+
+fn render_folder_picker(f: &mut Frame, picker: &mut FolderPicker, area: Rect) {
+    let folders: Vec<ListItem> = picker
+        .folders
+        .iter()
+        .zip(&picker.checked)
+        .map(|(folder, &checked)| {
+            let mut spans = Vec::new();
+            if let FolderPickerAction::Favorite = picker.action {
+                spans.push(Span::raw(if checked { "[x] " } else { "[ ] " }));
+            }
+            spans.push(Span::raw(folder.title.clone()));
+            spans.push(Span::raw(format!(" ({})", folder.media_count)).fg(Color::DarkGray));
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+    let folders_list = List::new(folders)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(picker.title())
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(folders_list, area, &mut picker.list_state);
+}
+
+fn render_history(f: &mut Frame, view: &mut HistoryView, area: Rect) {
+    const BAR_WIDTH: usize = 20;
+    let text_width = area.width.saturating_sub(6) as usize;
+    let entries: Vec<ListItem> = view
+        .entries
+        .iter()
+        .map(|entry| {
+            let watched_at = Local
+                .timestamp_opt(entry.view_at, 0)
+                .single()
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_default();
+            let filled = (entry.watched_ratio() * BAR_WIDTH as f64).round() as usize;
+            let position = if entry.progress < 0 {
+                "finished".to_string()
+            } else {
+                format!(
+                    "{} / {}",
+                    api::format_duration(entry.progress as u64),
+                    api::format_duration(entry.duration.max(0) as u64)
+                )
+            };
+
+            let mut lines: Vec<Line> = textwrap::wrap(&entry.title, text_width)
+                .iter()
+                .map(|s| Line::from(s.to_string()))
+                .collect();
+            lines.push(Line::from(
+                format!("{} · watched {}", entry.author_name, watched_at)
+                    .italic()
+                    .fg(Color::DarkGray),
+            ));
+            lines.push(Line::from(vec![
+                Span::raw("█".repeat(filled)).fg(Color::Green),
+                Span::raw("░".repeat(BAR_WIDTH - filled)).fg(Color::DarkGray),
+                Span::raw(format!(" {}", position)),
+            ]));
+            lines.push(Line::from(""));
+            ListItem::new(lines)
+        })
+        .collect();
+
+    let mut title = "History".to_string();
+    if let Some(date) = view.date {
+        title.push_str(&format!(" on {}", date));
+    }
+    if view.next.is_some() {
+        title.push_str(" [l] load more");
+    }
+    let history_list = List::new(entries)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(history_list, area, &mut view.list_state);
+}
+
+fn render_feed(f: &mut Frame, view: &mut FeedView, area: Rect) {
+    let now = Local::now().timestamp();
+    let text_width = area.width.saturating_sub(8) as usize;
+    let items: Vec<ListItem> = view
+        .items
+        .iter()
+        .map(|item| {
+            let marker = if item.pub_ts > view.last_seen {
+                Span::raw("● ").fg(Color::Cyan)
+            } else {
+                Span::raw("  ")
+            };
+            let mut lines: Vec<Line> = textwrap::wrap(&item.title, text_width)
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    let prefix = if i == 0 {
+                        marker.clone()
+                    } else {
+                        Span::raw("  ")
+                    };
+                    Line::from(vec![prefix, Span::raw(s.to_string())])
+                })
+                .collect();
+            let meta_info = format!(
+                "  {} · {} · {} (▶ {})",
+                item.author,
+                relative_time(item.pub_ts, now),
+                api::format_duration(item.duration.as_secs()),
+                format_play(item.play)
+            );
+            lines.push(Line::from(meta_info.italic().fg(Color::DarkGray)));
+            lines.push(Line::from(""));
+            ListItem::new(lines)
+        })
+        .collect();
+
+    let unread = view
+        .items
+        .iter()
+        .filter(|i| i.pub_ts > view.last_seen)
+        .count();
+    let mut title = format!("Feed ({} new)", unread);
+    if view.has_more {
+        title.push_str(" [l] load more");
+    }
+    let feed_list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(feed_list, area, &mut view.list_state);
+}
+
+fn render_live(f: &mut Frame, view: &mut LiveView, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(area);
+    let tabs = Tabs::new(view.tabs.iter().map(|tab| tab.label().to_string()))
+        .select(view.tab)
+        .highlight_style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(tabs, rows[0]);
+
+    let rooms: Vec<ListItem> = view
+        .rooms
+        .iter()
+        .map(|room| {
+            let byline = if room.area_name.is_empty() {
+                room.uname.clone()
+            } else {
+                format!("{} · {}", room.uname, room.area_name)
+            };
+            video_list_item(
+                &room.title,
+                &byline,
+                &room.online.to_string(),
+                rows[1].width,
+            )
+        })
+        .collect();
+    let rooms_list = List::new(rooms)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Live [h/l] area [r] refresh [Enter] watch [R] record")
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(rooms_list, rows[1], &mut view.list_state);
+}
+
+fn render_season(f: &mut Frame, view: &mut SeasonView, area: Rect) {
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(7), Constraint::Min(0)])
+        .split(area);
+
+    let season = &view.season;
+    let mut status = vec![Span::raw(season.new_ep.desc.clone())];
+    if season.rights.area_limit != 0 {
+        status.push("  Region restricted".red());
+    }
+    let header_text = vec![
+        Line::from(Span::raw(season.title.clone()).bold()),
+        Line::from(status),
+        Line::from(""),
+        Line::from(Span::raw(season.evaluate.clone()).italic()),
+    ];
+    let header = Paragraph::new(header_text)
+        .wrap(ratatui::widgets::Wrap { trim: true })
+        .block(
+            Block::default()
+                .title(format!("Season ss{}", season.season_id))
+                .borders(Borders::ALL),
+        );
+    f.render_widget(header, rows[0]);
+
+    let episodes: Vec<ListItem> = season
+        .episodes
+        .iter()
+        .map(|episode| {
+            let mut line = vec![Span::raw(episode.display_title())];
+            if !episode.badge.is_empty() {
+                line.push(Span::raw(format!(" [{}]", episode.badge)).fg(Color::Magenta));
+            }
+            line.push(
+                Span::raw(format!(
+                    "  {}",
+                    api::format_duration(episode.duration / 1000)
+                ))
+                .dark_gray(),
+            );
+            ListItem::new(Line::from(line))
+        })
+        .collect();
+    let episodes_list = List::new(episodes)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(format!("Episodes ({}) [Enter] play", season.episodes.len()))
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(episodes_list, rows[1], &mut view.list_state);
+}
+
+fn render_recordings(f: &mut Frame, recordings: &[Recording], state: &mut ListState, area: Rect) {
+    let items: Vec<ListItem> = recordings
+        .iter()
+        .map(|recording| {
+            let (marker, marker_style) = if recording.stopped.is_some() {
+                ("■ stopped", Style::default().fg(Color::DarkGray))
+            } else if recording.status.is_some() {
+                ("◌ waiting", Style::default().fg(Color::Yellow))
+            } else {
+                (
+                    "● REC",
+                    Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+                )
+            };
+            let header = Line::from(vec![
+                Span::styled(marker, marker_style),
+                Span::raw(format!("  {} (room {})", recording.name, recording.room_id)),
+            ]);
+            let mut progress = format!(
+                "    {} · {} · {} part{}",
+                api::format_duration(recording.elapsed().as_secs()),
+                format_bytes(recording.bytes),
+                recording.parts,
+                if recording.parts == 1 { "" } else { "s" },
+            );
+            if let Some(file) = &recording.file {
+                progress.push_str(&format!(" · {}", file.display()));
+            }
+            let mut lines = vec![header, Line::from(progress.dark_gray())];
+            if let Some(status) = recording
+                .status
+                .as_ref()
+                .filter(|_| recording.stopped.is_none())
+            {
+                lines.push(Line::from(format!("    {}", status).yellow()));
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+    let title = if recordings.is_empty() {
+        "Recordings (none yet, use :record <room_id> or R in :live)"
+    } else {
+        "Recordings [s] stop [d] dismiss stopped [q] back"
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(title)
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_stateful_widget(list, area, state);
+}
+
+/// Lays out the full details of `app.video_info`: cover, title and byline, counters, tags and
+/// description, top to bottom.
+fn render_video_info(f: &mut Frame, app: &mut App, area: Rect) {
+    let Some(info) = &app.video_info else {
+        return;
+    };
+    let relation = app.relation.clone().unwrap_or_default();
+    let now = Local::now().timestamp();
+    // Opened from a search: keep highlighting the words the search matched.
+    let matched: Vec<&str> = match app.results_source {
+        ResultsSource::Search => app
+            .search_results
+            .iter()
+            .find(|video| video.bvid == info.bvid)
+            .map(|video| {
+                [
+                    video.title.matched_words(),
+                    video.description.matched_words(),
+                ]
+                .concat()
+            })
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    };
+    let title = api::Highlighted::with_words(&info.title, matched.iter().copied());
+    let desc = api::Highlighted::with_words(&info.desc, matched.iter().copied());
+
+    let creators = if info.staff.is_empty() {
+        info.owner.name.clone()
+    } else {
+        info.staff
+            .iter()
+            .map(|staff| format!("{} ({})", staff.name, staff.title))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let mut facts = Vec::new();
+    if !info.tname.is_empty() {
+        facts.push(info.tname.clone());
+    }
+    facts.push(
+        if info.is_original() {
+            "原创"
+        } else {
+            "转载"
+        }
+        .to_string(),
+    );
+    facts.push(api::format_duration(info.duration));
+    if let Some(published) = Local.timestamp_opt(info.pubdate, 0).single() {
+        facts.push(format!(
+            "{} ({})",
+            relative_time(info.pubdate, now),
+            published.format("%Y-%m-%d %H:%M")
+        ));
+    }
+    let header = vec![
+        Line::from(highlighted_spans(&title, 0..title.text.len())).bold(),
+        Line::from(vec!["By ".dark_gray(), Span::raw(creators)]),
+        Line::from(facts.join(" · ").dark_gray()),
+    ];
+
+    let counter = |label: &'static str, value: u64, mine: Option<String>| {
+        let mut spans = vec![
+            format!("{:<10}", label).bold(),
+            Span::raw(format_count(value)),
+        ];
+        if let Some(mine) = mine {
+            spans.push(Span::raw(format!(" ✓ {}", mine)).green());
+        }
+        Line::from(spans)
+    };
+    let stats = [
+        (
+            counter("Plays", info.stat.view, None),
+            counter(
+                "Likes",
+                info.stat.like,
+                relation.like.then(|| "liked".to_string()),
+            ),
+        ),
+        (
+            counter("Danmaku", info.stat.danmaku, None),
+            counter(
+                "Coins",
+                info.stat.coin,
+                (relation.coin > 0).then(|| format!("gave {}", relation.coin)),
+            ),
+        ),
+        (
+            counter("Replies", info.stat.reply, None),
+            counter(
+                "Favorites",
+                info.stat.favorite,
+                relation.favorite.then(|| "favorited".to_string()),
+            ),
+        ),
+        (counter("Shares", info.stat.share, None), Line::from("")),
+    ];
+
+    let mut body = Vec::new();
+    if !info.tags.is_empty() {
+        let mut chips = Vec::new();
+        for (i, tag) in info.tags.iter().enumerate() {
+            let chip = Span::raw(format!(" #{} ", tag.tag_name));
+            chips.push(if app.selected_tag == Some(i) {
+                chip.black().on_cyan().bold()
+            } else {
+                chip.cyan().on_dark_gray()
+            });
+            chips.push(Span::raw(" "));
+        }
+        chips.push("[ ] select tag  # videos with tag".dark_gray());
+        body.push(Line::from(chips));
+        body.push(Line::from(""));
+    }
+    body.extend(highlighted_lines(&desc));
+    body.push(Line::from(""));
+    body.push(Line::from(
+        "[P]lay with mpv  [l]ike  [c]oin  [C] 2 coins  [t]riple  [f]avorite".bold(),
+    ));
+
+    let cover_height = if app.cover.current.is_some() {
+        (area.height / 2).min(12)
+    } else {
+        0
+    };
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(cover_height),
+            Constraint::Length(header.len() as u16 + 1),
+            Constraint::Length(stats.len() as u16 + 1),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    f.render_widget(
+        Paragraph::new(header).wrap(ratatui::widgets::Wrap { trim: true }),
+        rows[1],
+    );
+    let stat_columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[2]);
+    let (left, right): (Vec<Line>, Vec<Line>) = stats.into_iter().unzip();
+    f.render_widget(Paragraph::new(left), stat_columns[0]);
+    f.render_widget(Paragraph::new(right), stat_columns[1]);
+    f.render_widget(
+        Paragraph::new(body).wrap(ratatui::widgets::Wrap { trim: false }),
+        rows[3],
+    );
+
+    if let Some(cover) = app.cover.current.clone() {
+        render_cover(f, app, &cover.image, rows[0]);
+    }
+}
+
+/// Formats a count the way Bilibili does: as is below 10,000, then in 万 and 亿.
+fn format_count(count: u64) -> String {
+    // Tenths of `unit`, rounded half up, e.g. 15 for 1.5万.
+    let tenths = |unit: u64| (count.saturating_mul(10) + unit / 2) / unit;
+    let (tenths, unit) = match count {
+        0..=9_999 => return count.to_string(),
+        // The unit is picked after rounding, so 99,999,999 reads 1亿 rather than 10000万.
+        _ if tenths(10_000) < 100_000 => (tenths(10_000), "万"),
+        _ => (tenths(100_000_000), "亿"),
+    };
+    match tenths % 10 {
+        0 => format!("{}{}", tenths / 10, unit),
+        digit => format!("{}.{}{}", tenths / 10, digit, unit),
+    }
+}
+
+/// A play count for lists, or "--" where the API hides it.
+fn format_play(play: Option<u64>) -> String {
+    play.map_or_else(|| "--".to_string(), format_count)
+}
+
+/// Draws the cover with half blocks, or leaves `area` empty for the graphics protocol to fill
+/// after the frame. Popups hide it, since a graphics image would be drawn over them.
+fn render_cover(f: &mut Frame, app: &mut App, image: &image::RgbImage, area: Rect) {
+    if app.cover.protocol == cover::Protocol::HalfBlocks {
+        f.render_widget(Paragraph::new(cover::half_blocks(image, area)), area);
+    } else if app.folder_picker.is_none() && app.quality_picker.is_none() {
+        app.cover.area = Some(area);
+    }
+}
+
+/// Formats a byte count with binary units, e.g. `1.5 GiB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+/// Renders the newest chat messages that fit, so the panel scrolls as messages arrive.
+fn render_chat(f: &mut Frame, chat: &LiveChat, area: Rect) {
+    let width = area.width.saturating_sub(2).max(1) as usize;
+    let height = area.height.saturating_sub(2) as usize;
+
+    let mut lines: Vec<Line> = Vec::new();
+    for event in &chat.messages {
+        let (text, style) = match event {
+            ChatEvent::Danmaku { user, text } => (format!("{}: {}", user, text), Style::default()),
+            ChatEvent::Gift { user, gift, count } => (
+                format!("{} sent {} ×{}", user, gift, count),
+                Style::default().fg(Color::Yellow),
+            ),
+            ChatEvent::SuperChat {
+                user,
+                message,
+                price,
+            } => (
+                format!("[¥{}] {}: {}", price, user, message),
+                Style::default()
+                    .fg(Color::Magenta)
+                    .add_modifier(Modifier::BOLD),
+            ),
+            ChatEvent::Connected => (
+                format!("— connected to room {} —", chat.room_id),
+                Style::default().fg(Color::DarkGray),
+            ),
+            ChatEvent::Disconnected(reason) => (
+                format!("— disconnected: {} —", reason),
+                Style::default().fg(Color::Red),
+            ),
+            ChatEvent::Popularity(_) => continue,
+        };
+        lines.extend(
+            textwrap::wrap(&text, width)
+                .into_iter()
+                .map(|s| Line::styled(s.into_owned(), style)),
+        );
+    }
+    let visible = lines.split_off(lines.len().saturating_sub(height));
+
+    let title = format!(
+        "Chat (room {}, popularity {}) [c] leave",
+        chat.room_id, chat.popularity
+    );
+    let chat_panel =
+        Paragraph::new(visible).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(chat_panel, area);
+}
+
+/// Describes how long before `now` the Unix timestamp `ts` was, e.g. `3 hours ago`.
+fn relative_time(ts: i64, now: i64) -> String {
+    let elapsed = (now - ts).max(0);
+    let (amount, unit) = match elapsed {
+        0..=59 => return "just now".to_string(),
+        60..=3599 => (elapsed / 60, "minute"),
+        3600..=86_399 => (elapsed / 3600, "hour"),
+        86_400..=2_591_999 => (elapsed / 86_400, "day"),
+        2_592_000..=31_535_999 => (elapsed / 2_592_000, "month"),
+        _ => (elapsed / 31_536_000, "year"),
+    };
+    format!(
+        "{} {}{} ago",
+        amount,
+        unit,
+        if amount == 1 { "" } else { "s" }
+    )
+}
+
+fn render_quality_picker(f: &mut Frame, picker: &mut QualityPicker, area: Rect) {
+    let qualities: Vec<ListItem> = picker
+        .qualities
+        .iter()
+        .map(|quality| ListItem::new(quality.desc.clone()))
+        .collect();
+    let qualities_list = List::new(qualities)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Quality [Enter] play")
+                .border_style(Style::default().fg(Color::Green)),
+        )
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol(">> ");
+    f.render_widget(Clear, area);
+    f.render_stateful_widget(qualities_list, area, &mut picker.list_state);
+}
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Draws what the newest background task is doing onto the right of `area`'s top border.
+fn render_spinner(f: &mut Frame, label: &str, started: Instant, area: Rect) {
+    let frame =
+        SPINNER_FRAMES[(started.elapsed().as_millis() / 80) as usize % SPINNER_FRAMES.len()];
+    let text = format!(" {} {}… ", frame, label);
+    let area = Rect {
+        x: area.x + 1,
+        y: area.y,
+        width: area.width.saturating_sub(2),
+        height: 1,
+    };
+    let spinner = Paragraph::new(text)
+        .alignment(Alignment::Right)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(spinner, area);
+}
+
+/// A rectangle of the given percentage size centered within `area`.
+fn centered_rect(percent_x: u16, percent_y: u16, area: Rect) -> Rect {
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Renders a video as a wrapped title followed by a dimmed byline/play-count line.
+fn video_list_item<'a>(title: &str, byline: &str, play: &str, width: u16) -> ListItem<'a> {
+    highlighted_list_item(&api::Highlighted::plain(title), byline, play, width)
+}
+
+/// Like [`video_list_item`], with the words the search matched highlighted in the title.
+fn highlighted_list_item<'a>(
+    title: &api::Highlighted,
+    byline: &str,
+    play: &str,
+    width: u16,
+) -> ListItem<'a> {
+    let text_width = width.saturating_sub(6) as usize;
+    let options = textwrap::Options::new(text_width)
+        .initial_indent("")
+        .subsequent_indent("  ");
+
+    let title_wrapped = textwrap::wrap(&title.text, options);
+
+    // Wrapping only drops whitespace at the breaks, so each line can be found again in order.
+    let mut cursor = 0;
+    let mut lines: Vec<Line> = title_wrapped
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let content = if i == 0 {
+                line.as_ref()
+            } else {
+                line.trim_start_matches("  ")
+            };
+            match title.text[cursor..].find(content) {
+                Some(offset) => {
+                    let start = cursor + offset;
+                    cursor = start + content.len();
+                    let mut spans = vec![Span::raw(if i == 0 { "" } else { "  " })];
+                    spans.extend(highlighted_spans(title, start..cursor));
+                    Line::from(spans)
+                }
+                None => Line::from(line.to_string()),
+            }
+        })
+        .collect();
+
+    let meta_info = format!("{} (▶ {})", byline, play);
+    lines.push(Line::from(meta_info.italic().fg(Color::DarkGray)));
+    lines.push(Line::from("")); // Add blank line for spacing
+
+    ListItem::new(lines)
+}
+
+/// Style for the words a search matched.
+const MATCH_STYLE: Style = Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+
+/// The part of `text` within `range` as spans, with its matches in [`MATCH_STYLE`].
+fn highlighted_spans(text: &api::Highlighted, range: std::ops::Range<usize>) -> Vec<Span<'static>> {
+    let mut spans = Vec::new();
+    let mut position = range.start;
+    for matched in &text.matches {
+        let start = matched.start.max(position);
+        let end = matched.end.min(range.end);
+        if start >= end {
+            continue;
+        }
+        if position < start {
+            spans.push(Span::raw(text.text[position..start].to_string()));
+        }
+        spans.push(Span::styled(text.text[start..end].to_string(), MATCH_STYLE));
+        position = end;
+    }
+    if position < range.end {
+        spans.push(Span::raw(text.text[position..range.end].to_string()));
+    }
+    spans
+}
+
+/// Each line of `text` as a styled line, keeping its highlights.
+fn highlighted_lines(text: &api::Highlighted) -> Vec<Line<'static>> {
+    let mut start = 0;
+    text.text
+        .split('\n')
+        .map(|line| {
+            let range = start..start + line.len();
+            start = range.end + 1;
+            Line::from(highlighted_spans(text, range))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_counts_in_wan_and_yi() {
+        for (count, expected) in [
+            (0, "0"),
+            (9_999, "9999"),
+            (10_000, "1万"),
+            (15_000, "1.5万"),
+            (15_050, "1.5万"),
+            (123_456, "12.3万"),
+            (99_950_000, "9995万"),
+            (99_994_999, "9999.5万"),
+            (99_999_999, "1亿"),
+            (100_000_000, "1亿"),
+            (123_456_789, "1.2亿"),
+            (12_345_678_901, "123.5亿"),
+        ] {
+            assert_eq!(format_count(count), expected, "{}", count);
+        }
+    }
+
+    #[test]
+    fn formats_hidden_play_counts_as_dashes() {
+        for (play, expected) in [
+            (None, "--"),
+            (Some(9_999), "9999"),
+            (Some(10_000), "1万"),
+            (Some(99_999_999), "1亿"),
+            (Some(100_000_000), "1亿"),
+        ] {
+            assert_eq!(format_play(play), expected, "{:?}", play);
+        }
+    }
+
+    #[test]
+    fn describes_elapsed_time_in_the_largest_whole_unit() {
+        let now = 1_700_000_000;
+        let ago = |secs: i64| relative_time(now - secs, now);
+        assert_eq!(ago(0), "just now");
+        assert_eq!(ago(59), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3599), "59 minutes ago");
+        assert_eq!(ago(3600), "1 hour ago");
+        assert_eq!(ago(86_399), "23 hours ago");
+        assert_eq!(ago(86_400), "1 day ago");
+        assert_eq!(ago(2_591_999), "29 days ago");
+        assert_eq!(ago(2_592_000), "1 month ago");
+        assert_eq!(ago(31_535_999), "12 months ago");
+        assert_eq!(ago(31_536_000), "1 year ago");
+        assert_eq!(ago(3 * 31_536_000), "3 years ago");
+    }
+
+    #[test]
+    fn treats_future_timestamps_as_just_now() {
+        assert_eq!(relative_time(1_700_000_100, 1_700_000_000), "just now");
+    }
 }